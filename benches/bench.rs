@@ -1,22 +1,45 @@
 #![feature(portable_simd)]
 #![feature(slice_as_chunks)]
 
-use bevy::prelude::Vec3;
-use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
-use impls::vec3::Explosion;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use impls::cache;
+use impls::changes::STATIONARY_FRACTIONS;
+use impls::cold::{self, ColdCount, ColdSize};
+use impls::footprint::F32_PAYLOAD;
+use impls::fragmentation::{self, ARCHETYPE_COUNTS};
+use impls::inputs::InputDistribution;
+use impls::lifecycle::Operation;
+use impls::mxcsr::FlushDenormals;
+use impls::parallel::{self, BATCH_SIZES};
+use impls::prefetch::PREFETCH_DISTANCES;
+use impls::quantize::{Snorm16, F16};
+use impls::registry::{
+    self, generate_explosion, Case, CASES, CHANGE_CASES, LIFECYCLE_CASES, PARALLEL_CASES,
+    PREFETCH_CASES,
+};
+use impls::setup::Setup;
 use rand::prelude::*;
 
 mod impls;
 
-fn generate_explosion(rng: &mut ThreadRng) -> Explosion {
-    Explosion {
-        center: Vec3::new(
-            rng.gen_range(0.0..=1.0),
-            rng.gen_range(0.0..=1.0),
-            rng.gen_range(0.0..=1.0),
-        ),
-        radius_squared: rng.gen_range(0.0..=1.0),
+//Fractions of input components replaced by subnormals in the denormal benchmarks
+const SUBNORMAL_FRACTIONS: [f64; 4] = [0.0, 0.01, 0.1, 1.0];
+
+//Sizes every layout in a group runs at, the same for all of them so they stay comparable: a plain f32
+//position and velocity's working set at each fraction of each cache level (see impls::cache).  Where
+//sysfs doesn't describe the caches, 2^exp - 1 for each of the group's fallback exponents instead.
+fn group_sizes(fallback: impl Iterator<Item = u32>) -> Vec<i32> {
+    let levels = cache::levels();
+    if levels.is_empty() {
+        return fallback.map(|exp| 2_i32.pow(exp) - 1).collect();
     }
+
+    let mut sizes: Vec<i32> = cache::sizes(&levels, F32_PAYLOAD as f64)
+        .into_iter()
+        .map(|size| size | 15) //Ensure scalar path gets run too
+        .collect();
+    sizes.dedup();
+    sizes
 }
 
 fn bench(c: &mut Criterion) {
@@ -26,9 +49,7 @@ fn bench(c: &mut Criterion) {
 
     //TODO: does it make sense to let Criterion specify the world size??
 
-    for exp in 14..16 {
-        let size = 2_i32.pow(exp) - 1; //Ensure scalar path gets run too
-
+    for size in group_sizes(14..16) {
         group.throughput(criterion::Throughput::Elements(size as u64));
 
         /*group.bench_with_input(BenchmarkId::new("vec3", size), &size, |b, &size| {
@@ -222,6 +243,42 @@ fn bench(c: &mut Criterion) {
             },
         );
         */
+
+        group.bench_with_input(
+            BenchmarkId::new("vec3_f16_nochangedetect", size),
+            &size,
+            |b, &size| {
+                let mut bench = impls::vec3_quantized::Benchmark::<F16>::new(size);
+                b.iter(move || bench.run_nochange(rand::thread_rng().gen_range(0.0..=1.0)));
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("vec3_snorm16_nochangedetect", size),
+            &size,
+            |b, &size| {
+                let mut bench = impls::vec3_quantized::Benchmark::<Snorm16>::new(size);
+                b.iter(move || bench.run_nochange(rand::thread_rng().gen_range(0.0..=1.0)));
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("aosoa_sse4_f16_nochangedetect", size),
+            &size,
+            |b, &size| {
+                let mut bench = impls::aosoa_sse4_quantized::Benchmark::<F16>::new(size);
+                b.iter(move || bench.run_nochange(rand::thread_rng().gen_range(0.0..=1.0)));
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("aosoa_sse4_snorm16_nochangedetect", size),
+            &size,
+            |b, &size| {
+                let mut bench = impls::aosoa_sse4_quantized::Benchmark::<Snorm16>::new(size);
+                b.iter(move || bench.run_nochange(rand::thread_rng().gen_range(0.0..=1.0)));
+            },
+        );
     }
 
     //TODO: ensure LTO, O3, -ffast-math, etc...
@@ -230,5 +287,541 @@ fn bench(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench);
+//Runs each layout's update on inputs with a controlled fraction of subnormals, with the MXCSR
+//flush-to-zero and denormals-are-zero bits both off and on
+fn bench_denormals(c: &mut Criterion) {
+    let mut group = c.benchmark_group("denormal_benchmarks");
+    group.warm_up_time(std::time::Duration::from_secs(1));
+    group.measurement_time(std::time::Duration::from_secs(9));
+
+    for size in group_sizes(14..16) {
+        group.throughput(criterion::Throughput::Elements(size as u64));
+
+        for case in registry::setup_cases() {
+            let build = case.build_with_setup.unwrap();
+
+            for subnormal_fraction in SUBNORMAL_FRACTIONS {
+                let setup =
+                    Setup::with_inputs(InputDistribution::with_subnormals(subnormal_fraction));
+
+                for ftz_daz in [false, true] {
+                    let id = format!(
+                        "{}_{}_subnormal_{}_ftz_daz_{}",
+                        case.layout,
+                        case.workload,
+                        subnormal_fraction,
+                        if ftz_daz { "on" } else { "off" }
+                    );
+
+                    group.bench_with_input(BenchmarkId::new(id, size), &size, |b, &size| {
+                        let mut kernel = build(size, &setup);
+                        let mut rng = rand::thread_rng();
+                        let _mxcsr = FlushDenormals::new(ftz_daz);
+                        b.iter(move || kernel(&mut rng));
+                    });
+                }
+            }
+        }
+    }
+
+    group.finish();
+}
+
+//Compares the plain AoSoA and SoA kernels against software prefetching and non-temporal store variants.
+//Sizes go well past the LLC since that's where these are expected to start paying off.
+fn bench_prefetch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("prefetch_benchmarks");
+    group.warm_up_time(std::time::Duration::from_secs(1));
+    group.measurement_time(std::time::Duration::from_secs(9));
+
+    for size in group_sizes((14..24).step_by(3)) {
+        group.throughput(criterion::Throughput::Elements(size as u64));
+
+        for case in PREFETCH_CASES {
+            let stream = format!("{}_stream", case.workload);
+            bench_case(
+                &mut group,
+                registry::find(case.layout, case.workload).unwrap(),
+                size,
+            );
+            bench_case(
+                &mut group,
+                registry::find(case.layout, &stream).unwrap(),
+                size,
+            );
+
+            for distance in PREFETCH_DISTANCES {
+                group.bench_with_input(
+                    BenchmarkId::new(
+                        format!("{}_{}_prefetch_{}", case.layout, case.workload, distance),
+                        size,
+                    ),
+                    &size,
+                    |b, &size| {
+                        let mut kernel = (case.build)(size, distance);
+                        let mut rng = rand::thread_rng();
+                        b.iter(move || kernel(&mut rng));
+                    },
+                );
+            }
+        }
+    }
+
+    group.finish();
+}
+
+//Glam and std::simd implementations of the AoSoA and simulated SoA layouts side by side.  Lane count 4 is
+//directly comparable with the glam Vec4 kernels, the wider ones show what std::simd can do beyond that.
+fn bench_portable_simd(c: &mut Criterion) {
+    let mut group = c.benchmark_group("portable_simd_benchmarks");
+    group.warm_up_time(std::time::Duration::from_secs(1));
+    group.measurement_time(std::time::Duration::from_secs(9));
+
+    for size in group_sizes(14..16) {
+        group.throughput(criterion::Throughput::Elements(size as u64));
+
+        for case in registry::portable_simd_cases() {
+            bench_case(&mut group, case, size);
+        }
+    }
+
+    group.finish();
+}
+
+//Damped oscillator workload (sqrt, exp, sin, cos per virtual entity) in scalar libm, glam and polynomial form,
+//for every layout the registry has it for
+fn bench_transcendental(c: &mut Criterion) {
+    let mut group = c.benchmark_group("transcendental_benchmarks");
+    group.warm_up_time(std::time::Duration::from_secs(1));
+    group.measurement_time(std::time::Duration::from_secs(9));
+
+    for size in group_sizes(14..16) {
+        group.throughput(criterion::Throughput::Elements(size as u64));
+
+        for case in CASES
+            .iter()
+            .filter(|case| case.workload.starts_with("oscillator"))
+        {
+            bench_case(&mut group, case, size);
+        }
+    }
+
+    group.finish();
+}
+
+//Every layout/workload with a known memory footprint, reported as bytes/s so criterion shows the
+//bandwidth each layout actually uses.  Each layout is sized so its working set sits at 50%, 100% and 200%
+//of every cache level, so in-cache and memory bound behaviour are both covered.
+fn bench_bandwidth(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bandwidth_benchmarks");
+    group.warm_up_time(std::time::Duration::from_secs(1));
+    group.measurement_time(std::time::Duration::from_secs(9));
+
+    let levels = cache::levels();
+
+    //The system cases are timed by bench_systems, next to their QueryState kernels
+    for case in CASES.iter().filter(|case| case.workload != "update_system") {
+        let traffic = match case.traffic {
+            Some(traffic) => traffic(),
+            None => continue,
+        };
+
+        //Without a cache hierarchy to size against, sweep from L1 to well past the LLC
+        let sizes = match registry::bytes_per_element(case.layout) {
+            Some(bytes) if !levels.is_empty() => cache::sizes(&levels, bytes),
+            _ => (14..24)
+                .step_by(3)
+                .map(|exp| 2_i32.pow(exp) - 1) //Ensure scalar path gets run too
+                .collect(),
+        };
+
+        for size in sizes {
+            group.throughput(criterion::Throughput::Bytes(
+                (traffic.bytes() * size as f64) as u64,
+            ));
+
+            bench_case(&mut group, case, size);
+        }
+    }
+
+    group.finish();
+}
+
+//Each layout's update kernel as a regular system run through a Schedule, next to the same kernel run
+//straight through QueryState where the registry has one.  The difference is what the scheduler costs
+//per layout.
+fn bench_systems(c: &mut Criterion) {
+    let mut group = c.benchmark_group("system_benchmarks");
+    group.warm_up_time(std::time::Duration::from_secs(1));
+    group.measurement_time(std::time::Duration::from_secs(9));
+
+    for size in group_sizes(14..16) {
+        group.throughput(criterion::Throughput::Elements(size as u64));
+
+        for system in CASES.iter().filter(|case| case.workload == "update_system") {
+            let raw = registry::find(system.layout, "update");
+            for case in raw.into_iter().chain([system]) {
+                bench_case(&mut group, case, size);
+            }
+        }
+    }
+
+    group.finish();
+}
+
+//Each layout's update kernel with its components in sparse sets, next to the same kernel over tables.
+//Sparse set iteration looks every component up through the entity, so the gap is what that indirection
+//costs each layout.
+fn bench_storage(c: &mut Criterion) {
+    let mut group = c.benchmark_group("storage_benchmarks");
+    group.warm_up_time(std::time::Duration::from_secs(1));
+    group.measurement_time(std::time::Duration::from_secs(9));
+
+    for size in group_sizes(14..16) {
+        group.throughput(criterion::Throughput::Elements(size as u64));
+
+        for sparse in CASES.iter().filter(|case| case.workload == "update_sparse") {
+            let table = registry::find(sparse.layout, "update");
+            for case in table.into_iter().chain([sparse]) {
+                bench_case(&mut group, case, size);
+            }
+        }
+    }
+
+    group.finish();
+}
+
+//Each layout's update kernel with the same entities spread over more and more archetypes by marker
+//components.  spawn_batch's single archetype is the best case; per-archetype overhead (table switches,
+//shorter runs the kernel can vectorize over) grows with the archetype count.
+fn bench_fragmentation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fragmentation_benchmarks");
+    group.warm_up_time(std::time::Duration::from_secs(1));
+    group.measurement_time(std::time::Duration::from_secs(9));
+
+    for size in group_sizes((14..20).step_by(3)) {
+        group.throughput(criterion::Throughput::Elements(size as u64));
+
+        for case in registry::setup_cases() {
+            let build = case.build_with_setup.unwrap();
+
+            for archetypes in ARCHETYPE_COUNTS {
+                group.bench_with_input(
+                    BenchmarkId::new(
+                        format!(
+                            "{}_{}_{}_archetypes",
+                            case.layout, case.workload, archetypes
+                        ),
+                        size,
+                    ),
+                    &size,
+                    |b, &size| {
+                        let fragment = |world| fragmentation::fragment(world, archetypes);
+                        let mut kernel = build(size, &Setup::with_transform(&fragment));
+                        let mut rng = rand::thread_rng();
+                        b.iter(move || kernel(&mut rng));
+                    },
+                );
+            }
+        }
+    }
+
+    group.finish();
+}
+
+//Each layout's update kernel with every entity also carrying unrelated components of various sizes,
+//next to the plain layout.  Table columns should keep the hot data contiguous whatever else lives in
+//the table, so any slowdown is down to allocation interleaving and the extra table moves.  Run at sizes
+//past the caches, where that would show.
+fn bench_cold(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cold_benchmarks");
+    group.warm_up_time(std::time::Duration::from_secs(1));
+    group.measurement_time(std::time::Duration::from_secs(9));
+
+    for size in group_sizes((16..22).step_by(3)) {
+        group.throughput(criterion::Throughput::Elements(size as u64));
+
+        for case in registry::setup_cases() {
+            let build = case.build_with_setup.unwrap();
+            bench_case(&mut group, case, size);
+
+            for &bytes in ColdSize::ALL {
+                for &count in ColdCount::ALL {
+                    group.bench_with_input(
+                        BenchmarkId::new(
+                            format!(
+                                "{}_{}_{}_cold_{}_bytes",
+                                case.layout,
+                                case.workload,
+                                count.count(),
+                                bytes.bytes()
+                            ),
+                            size,
+                        ),
+                        &size,
+                        |b, &size| {
+                            let add_cold = |world| cold::add_cold(world, count, bytes);
+                            let mut kernel = build(size, &Setup::with_transform(&add_cold));
+                            let mut rng = rand::thread_rng();
+                            b.iter(move || kernel(&mut rng));
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    group.finish();
+}
+
+//Change detection from both ends: each layout's update writing only the virtual entities that move,
+//alone and followed by a `Changed<Position>` query reading back what it changed, over a range of
+//stationary fractions.  Packed layouts mark a whole entity changed when any of its lanes moves, so
+//their consumer visits more virtual entities than actually moved; `cargo run -- changes` prints how many.
+fn bench_changes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("change_detection_benchmarks");
+    group.warm_up_time(std::time::Duration::from_secs(1));
+    group.measurement_time(std::time::Duration::from_secs(9));
+
+    for size in group_sizes(14..16) {
+        group.throughput(criterion::Throughput::Elements(size as u64));
+
+        for case in CHANGE_CASES {
+            if let Some(plain) = registry::find(case.layout, "update") {
+                bench_case(&mut group, plain, size);
+            }
+
+            for stationary_fraction in STATIONARY_FRACTIONS {
+                for consume in [false, true] {
+                    let suffix = if consume { "_changed_consumer" } else { "" };
+
+                    group.bench_with_input(
+                        BenchmarkId::new(
+                            format!(
+                                "{}_update_stationary_{}{}",
+                                case.layout, stationary_fraction, suffix
+                            ),
+                            size,
+                        ),
+                        &size,
+                        |b, &size| {
+                            let (_, mut kernel) = (case.build)(size, stationary_fraction);
+                            let mut rng = rand::thread_rng();
+                            b.iter(move || kernel(&mut rng, consume));
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    group.finish();
+}
+
+//Every virtual entity inheriting the velocity of a parent looked up with Query::get, with parents in
+//random, sorted, clustered and own order, next to the linear update where the layout has one.  AoSoA
+//lookups return a whole block, of which only one lane is used.
+fn bench_gather(c: &mut Criterion) {
+    let mut group = c.benchmark_group("gather_benchmarks");
+    group.warm_up_time(std::time::Duration::from_secs(1));
+    group.measurement_time(std::time::Duration::from_secs(9));
+
+    let gathers: Vec<&Case> = CASES
+        .iter()
+        .filter(|case| case.workload.starts_with("gather_"))
+        .collect();
+
+    for size in group_sizes((14..23).step_by(4)) {
+        group.throughput(criterion::Throughput::Elements(size as u64));
+
+        let linear = CASES.iter().filter(|case| {
+            case.workload == "update" && gathers.iter().any(|gather| gather.layout == case.layout)
+        });
+
+        for case in linear.chain(gathers.iter().copied()) {
+            bench_case(&mut group, case, size);
+        }
+    }
+
+    group.finish();
+}
+
+//True SoA over whole table columns, one entity per element, against the same layout through regular
+//queries and against the "virtual entity" simulation packing 4 elements into each entity
+fn bench_columns(c: &mut Criterion) {
+    let mut group = c.benchmark_group("column_benchmarks");
+    group.warm_up_time(std::time::Duration::from_secs(1));
+    group.measurement_time(std::time::Duration::from_secs(9));
+
+    for size in group_sizes((14..23).step_by(4)) {
+        group.throughput(criterion::Throughput::Elements(size as u64));
+
+        let cases = CASES.iter().filter(|case| {
+            case.layout == "soa_columns"
+                || (case.layout == "simd_batch_4_soa_simulated"
+                    && ["update", "update_nochangedetect"].contains(&case.workload))
+        });
+
+        for case in cases {
+            bench_case(&mut group, case, size);
+        }
+    }
+
+    group.finish();
+}
+
+//Spawning each layout's entities with spawn_batch, spawn and Commands, and inserting and removing
+//their velocity components afterwards.  Each iteration starts from a freshly built world, and building
+//it is left out of the timing.
+fn bench_lifecycle(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lifecycle_benchmarks");
+    group.warm_up_time(std::time::Duration::from_secs(1));
+    group.measurement_time(std::time::Duration::from_secs(9));
+
+    for size in group_sizes((12..21).step_by(4)) {
+        group.throughput(criterion::Throughput::Elements(size as u64));
+
+        for case in LIFECYCLE_CASES {
+            for operation in Operation::ALL {
+                group.bench_with_input(
+                    BenchmarkId::new(format!("{}_{}", case.layout, operation.name()), size),
+                    &size,
+                    |b, &size| {
+                        b.iter_batched(
+                            || (case.prepare)(size, operation),
+                            |run| run(),
+                            BatchSize::LargeInput,
+                        );
+                    },
+                );
+            }
+        }
+    }
+
+    group.finish();
+}
+
+//Converting a world of AoS entities to aosoa_sse4 blocks and back, next to an update of each layout,
+//for how many frames a switch has to last to pay for itself
+fn bench_migration(c: &mut Criterion) {
+    let mut group = c.benchmark_group("migration_benchmarks");
+    group.warm_up_time(std::time::Duration::from_secs(1));
+    group.measurement_time(std::time::Duration::from_secs(9));
+
+    for size in group_sizes((14..23).step_by(4)) {
+        group.throughput(criterion::Throughput::Elements(size as u64));
+
+        let updates = CASES.iter().filter(|case| {
+            ["vec3_aos", "aosoa_sse4"].contains(&case.layout) && case.workload == "update"
+        });
+
+        for case in updates {
+            bench_case(&mut group, case, size);
+        }
+
+        group.bench_with_input(
+            BenchmarkId::new("vec3_aos_to_aosoa_sse4", size),
+            &size,
+            |b, &size| {
+                b.iter_batched(
+                    || impls::vec3_aos::Benchmark::new(size),
+                    impls::aosoa_sse4::Benchmark::from_aos,
+                    BatchSize::LargeInput,
+                );
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("aosoa_sse4_to_vec3_aos", size),
+            &size,
+            |b, &size| {
+                b.iter_batched(
+                    || {
+                        let aos = impls::vec3_aos::Benchmark::new(size);
+                        impls::aosoa_sse4::Benchmark::from_aos(aos)
+                    },
+                    |(bench, lanes)| bench.into_aos(lanes),
+                    BatchSize::LargeInput,
+                );
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_case(
+    group: &mut criterion::BenchmarkGroup<criterion::measurement::WallTime>,
+    case: &Case,
+    size: i32,
+) {
+    group.bench_with_input(
+        BenchmarkId::new(format!("{}_{}", case.layout, case.workload), size),
+        &size,
+        |b, &size| {
+            let mut kernel = (case.build)(size);
+            let mut rng = rand::thread_rng();
+            b.iter(move || kernel(&mut rng));
+        },
+    );
+}
+
+//Each layout's update kernel with par_for_each_mut, sweeping the entities per task.  The pool can only
+//be built once per process, so the thread count comes from SIMD_BENCH_THREADS (default: one per core)
+//and goes into the id; sweep it by rerunning with different values.
+fn bench_parallel(c: &mut Criterion) {
+    let threads = parallel::init_task_pool(parallel::threads_from_env());
+
+    let mut group = c.benchmark_group("parallel_benchmarks");
+    group.warm_up_time(std::time::Duration::from_secs(1));
+    group.measurement_time(std::time::Duration::from_secs(9));
+
+    for size in group_sizes((16..22).step_by(3)) {
+        group.throughput(criterion::Throughput::Elements(size as u64));
+
+        for case in PARALLEL_CASES {
+            for batch_size in BATCH_SIZES {
+                group.bench_with_input(
+                    BenchmarkId::new(
+                        format!(
+                            "{}_update_par_{}_threads_batch_{}",
+                            case.layout, threads, batch_size
+                        ),
+                        size,
+                    ),
+                    &size,
+                    |b, &size| {
+                        let mut kernel = (case.build)(size);
+                        let mut rng = rand::thread_rng();
+                        b.iter(move || kernel(&mut rng, batch_size));
+                    },
+                );
+            }
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    //First, since the compute pool can only be built once and any Schedule or par_for_each_mut run
+    //before it would build one with the default thread count
+    bench_parallel,
+    bench,
+    bench_denormals,
+    bench_prefetch,
+    bench_portable_simd,
+    bench_transcendental,
+    bench_bandwidth,
+    bench_storage,
+    bench_fragmentation,
+    bench_cold,
+    bench_changes,
+    bench_gather,
+    bench_columns,
+    bench_lifecycle,
+    bench_migration,
+    bench_systems
+);
 criterion_main!(benches);
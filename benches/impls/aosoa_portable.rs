@@ -0,0 +1,383 @@
+use bevy::ecs::component::TableStorage;
+use bevy::ecs::system::SystemState;
+use bevy::prelude::*;
+use rand::rngs::ThreadRng;
+
+use std::marker::PhantomData;
+use std::simd::num::SimdFloat;
+use std::simd::{LaneCount, Simd, SupportedLaneCount};
+
+use super::changes::{ChangeBenchmark, Lanes};
+use super::footprint::{Footprint, F32_PAYLOAD};
+use super::gather::{self, LaneParents, ParentOrder};
+use super::inputs::InputDistribution;
+use super::lifecycle::{self, Operation, Prepared};
+use super::prefetch::{prefetch, stream, stream_fence};
+use super::setup::Setup;
+use super::storage::Storage;
+use super::system::{SystemBenchmark, TimeStep};
+use super::traffic::Traffic;
+use super::transcendental::{oscillate_scalar, oscillate_simd, LaneMath};
+
+//Same layout as aosoa_sse4, but N-wide and using std::simd instead of glam
+#[derive(Copy, Clone)]
+struct AoSoAVec3<const N: usize>
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    v: [Simd<f32, N>; 3],
+}
+
+impl<const N: usize> AoSoAVec3<N>
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    fn new(x: Simd<f32, N>, y: Simd<f32, N>, z: Simd<f32, N>) -> Self {
+        AoSoAVec3 { v: [x, y, z] }
+    }
+}
+
+pub struct Position<const N: usize, S: Storage = TableStorage>(AoSoAVec3<N>, PhantomData<S>)
+where
+    LaneCount<N>: SupportedLaneCount;
+
+impl<const N: usize, S: Storage> Component for Position<N, S>
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    type Storage = S;
+}
+
+pub struct Velocity<const N: usize, S: Storage = TableStorage>(AoSoAVec3<N>, PhantomData<S>)
+where
+    LaneCount<N>: SupportedLaneCount;
+
+impl<const N: usize, S: Storage> Component for Velocity<N, S>
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    type Storage = S;
+}
+
+pub struct Benchmark<'w, const N: usize, S: Storage = TableStorage>(
+    World,
+    QueryState<(&'w Velocity<N, S>, &'w mut Position<N, S>)>,
+)
+where
+    LaneCount<N>: SupportedLaneCount;
+
+//Bytes touched per virtual entity by the update kernel
+pub fn traffic<const N: usize>() -> Traffic
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    Traffic::update::<Velocity<N>, Position<N>>(N)
+}
+
+//Memory the world holds for this layout
+pub fn footprint<const N: usize>(size: i32) -> Footprint
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    Footprint::of(&Benchmark::<N>::new(size).0, N, F32_PAYLOAD)
+}
+
+//Spawning, inserting into or removing from `size` virtual entities of this layout, see lifecycle.rs
+pub fn lifecycle<const N: usize>(size: i32, operation: Operation) -> Prepared
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    lifecycle::prepare(
+        size as usize / N,
+        operation,
+        moving_bundle::<N, TableStorage>,
+        position_bundle::<N, TableStorage>,
+        velocity_bundle::<N, TableStorage>,
+    )
+}
+
+fn moving_bundle<const N: usize, S: Storage>(
+    inputs: &InputDistribution,
+    rng: &mut ThreadRng,
+) -> (Position<N, S>, Velocity<N, S>)
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    let (position,) = position_bundle(inputs, rng);
+    let (velocity,) = velocity_bundle(inputs, rng);
+    (position, velocity)
+}
+
+fn position_bundle<const N: usize, S: Storage>(
+    inputs: &InputDistribution,
+    rng: &mut ThreadRng,
+) -> (Position<N, S>,)
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    let [pxs, pys, pzs] =
+        [0, 1, 2].map(|_| Simd::from_array([(); N].map(|_| inputs.position(rng))));
+    (Position(AoSoAVec3::new(pxs, pys, pzs), PhantomData),)
+}
+
+fn velocity_bundle<const N: usize, S: Storage>(
+    inputs: &InputDistribution,
+    rng: &mut ThreadRng,
+) -> (Velocity<N, S>,)
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    let [vxs, vys, vzs] =
+        [0, 1, 2].map(|_| Simd::from_array([(); N].map(|_| inputs.velocity(rng))));
+    (Velocity(AoSoAVec3::new(vxs, vys, vzs), PhantomData),)
+}
+
+impl<'w, const N: usize> Benchmark<'w, N>
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    pub fn new(size: i32) -> Self {
+        Self::with_storage(size)
+    }
+
+    //The world spawned from `setup.inputs`, then passed through `setup.transform`
+    pub fn with_setup(size: i32, setup: &Setup) -> Self {
+        let mut bench = Self::spawn(size, &setup.inputs);
+        bench.0 = (setup.transform)(std::mem::take(&mut bench.0));
+        bench
+    }
+}
+
+impl<'w, const N: usize, S: Storage> Benchmark<'w, N, S>
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    pub fn with_storage(size: i32) -> Self {
+        Self::spawn(size, &InputDistribution::default())
+    }
+
+    fn spawn(size: i32, inputs: &InputDistribution) -> Self {
+        let size = size / N as i32; //N virtual entities per entity
+
+        let mut world = World::new();
+
+        let mut rng = rand::thread_rng();
+
+        world.spawn_batch((0..size).map(|_| moving_bundle::<N, S>(inputs, &mut rng)));
+
+        let query = world.query::<(&Velocity<N, S>, &mut Position<N, S>)>();
+        Self(world, query)
+    }
+
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run(&mut self, time: f32) {
+        let time = Simd::splat(time);
+
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                update(time, velocity, &mut position)
+            });
+    }
+
+    //Same as run, split over the ComputeTaskPool in tasks of `batch_size` entities
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_par(&mut self, time: f32, batch_size: usize) {
+        let time = Simd::splat(time);
+
+        self.1
+            .par_for_each_mut(&mut self.0, batch_size, |(velocity, mut position)| {
+                update(time, velocity, &mut position)
+            });
+    }
+
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_nochange(&mut self, time: f32) {
+        let time = Simd::splat(time);
+
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                update(time, velocity, position.bypass_change_detection())
+            });
+    }
+
+    //Same as run, but prefetches the blocks `distance` entities ahead
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_prefetch(&mut self, time: f32, distance: usize) {
+        let time = Simd::splat(time);
+
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                prefetch(velocity, distance);
+                prefetch(&*position, distance);
+                update(time, velocity, &mut position)
+            });
+    }
+
+    //Same as run, but writes the results with non-temporal stores
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_stream(&mut self, time: f32) {
+        let time = Simd::splat(time);
+
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                update_with(time, velocity, &mut position, stream)
+            });
+        stream_fence();
+    }
+
+    //Transcendental workload, one virtual entity (lane) at a time through libm
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_oscillator_libm(&mut self, time: f32) {
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                let [pxs, pys, pzs] = &mut position.0.v;
+                let [vxs, vys, vzs] = &velocity.0.v;
+                for i in 0..N {
+                    let p = Vec3::new(pxs[i], pys[i], pzs[i]);
+                    let v = Vec3::new(vxs[i], vys[i], vzs[i]);
+                    let n = oscillate_scalar(p, v, time);
+                    pxs[i] = n.x;
+                    pys[i] = n.y;
+                    pzs[i] = n.z;
+                }
+            });
+    }
+
+    //Transcendental workload, 4 of the N lanes at a time
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_oscillator<M: LaneMath>(&mut self, time: f32) {
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                position.0.v = oscillate_simd::<M, N>(position.0.v, velocity.0.v, time);
+            });
+    }
+}
+
+//The update kernel of run, run_par, run_nochange, run_prefetch and the system
+#[inline(always)]
+fn update<const N: usize, S: Storage>(
+    time: Simd<f32, N>,
+    velocity: &Velocity<N, S>,
+    position: &mut Position<N, S>,
+) where
+    LaneCount<N>: SupportedLaneCount,
+{
+    update_with(time, velocity, position, |dst, value| *dst = value)
+}
+
+//The update kernel, writing each axis back through `store`
+#[inline(always)]
+fn update_with<const N: usize, S: Storage>(
+    time: Simd<f32, N>,
+    velocity: &Velocity<N, S>,
+    position: &mut Position<N, S>,
+    store: impl Fn(&mut Simd<f32, N>, Simd<f32, N>),
+) where
+    LaneCount<N>: SupportedLaneCount,
+{
+    for (p, v) in position.0.v.iter_mut().zip(&velocity.0.v) {
+        store(p, *p + time * *v);
+    }
+}
+
+//The update kernel as a regular system
+fn update_system<const N: usize>(
+    time: Res<TimeStep>,
+    mut query: Query<(&Velocity<N>, &mut Position<N>)>,
+) where
+    LaneCount<N>: SupportedLaneCount,
+{
+    let time = Simd::splat(time.0);
+    query.for_each_mut(|(velocity, mut position)| update(time, velocity, &mut position));
+}
+
+pub fn system_benchmark<const N: usize>(size: i32) -> SystemBenchmark
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    SystemBenchmark::new(Benchmark::<N>::new(size).0, update_system::<N>)
+}
+
+impl<const N: usize> Lanes<Position<N>> for Velocity<N>
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    const LANES: usize = N;
+
+    #[inline(always)]
+    fn update(time: f32, velocity: &Self, position: &mut Position<N>) {
+        update(Simd::splat(time), velocity, position)
+    }
+
+    #[inline(always)]
+    fn moving(&self) -> bool {
+        self.0.v.iter().any(|v| *v != Simd::splat(0.0))
+    }
+
+    fn stop(&mut self, lane: usize) {
+        for axis in self.0.v.iter_mut() {
+            axis[lane] = 0.0;
+        }
+    }
+
+    #[inline(always)]
+    fn read(position: &Position<N>) -> f32 {
+        (position.0.v[0] + position.0.v[1] + position.0.v[2]).reduce_sum()
+    }
+}
+
+//Change detection benchmark: stationary virtual entities and a `Changed<Position>` consumer.  A block
+//is marked changed if any of its N lanes moves.
+pub fn change_benchmark<const N: usize>(
+    size: i32,
+    stationary_fraction: f64,
+) -> ChangeBenchmark<Position<N>, Velocity<N>>
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    ChangeBenchmark::new(Benchmark::<N>::new(size).0, stationary_fraction)
+}
+
+type GatherParams<const N: usize> = (
+    Query<'static, 'static, (&'static LaneParents<N>, &'static mut Position<N>)>,
+    Query<'static, 'static, &'static Velocity<N>>,
+);
+
+//Gather workload: every virtual entity inherits the velocity of a parent looked up with Query::get.
+//Each lane's parent can sit in a different block, so one block takes N lookups and a lane shuffle.
+pub struct GatherBenchmark<const N: usize>(World, SystemState<GatherParams<N>>)
+where
+    LaneCount<N>: SupportedLaneCount;
+
+impl<const N: usize> GatherBenchmark<N>
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    pub fn new(size: i32, order: ParentOrder) -> Self {
+        let mut world = Benchmark::<N>::new(size).0;
+        gather::assign_lane_parents::<Position<N>, N>(&mut world, order);
+
+        let state = SystemState::new(&mut world);
+        Self(world, state)
+    }
+
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run(&mut self, time: f32) {
+        let time = Simd::splat(time);
+        let (mut children, velocities) = self.1.get_mut(&mut self.0);
+
+        children.for_each_mut(|(parents, mut position)| {
+            let mut v = [Simd::splat(0.0); 3];
+            for (lane, &(parent, parent_lane)) in parents.0.iter().enumerate() {
+                let velocity = &velocities.get(parent).unwrap().0;
+                for (v, parent_v) in v.iter_mut().zip(&velocity.v) {
+                    v[lane] = parent_v[parent_lane];
+                }
+            }
+
+            position.0.v[0] += time * v[0];
+            position.0.v[1] += time * v[1];
+            position.0.v[2] += time * v[2];
+        });
+    }
+}
@@ -4,7 +4,24 @@ use rand::prelude::*;
 
 use std::marker::PhantomData;
 
+use bevy::ecs::component::TableStorage;
+use bevy::ecs::system::SystemState;
 use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use super::changes::{ChangeBenchmark, Lanes};
+use super::footprint::{Footprint, F32_PAYLOAD};
+use super::gather::{self, LaneParents, ParentOrder};
+use super::inputs::InputDistribution;
+use super::lifecycle::{self, Operation, Prepared};
+use super::migration::LaneMap;
+use super::prefetch::{prefetch, stream, stream_fence};
+use super::setup::Setup;
+use super::storage::Storage;
+use super::system::{SystemBenchmark, TimeStep};
+use super::traffic::Traffic;
+use super::transcendental::{oscillate_lanes, oscillate_scalar, LaneMath};
+use super::vec3_aos;
 
 //Each AoSoAVec3 is 4-wide
 //FIXME: use nalgebra to use their SIMD types?  or simba?
@@ -17,79 +34,356 @@ impl AoSoAVec3 {
     fn new(x: Vec4, y: Vec4, z: Vec4) -> Self {
         AoSoAVec3 { v: [x, y, z] }
     }
+
+    fn from_lanes(v: [Vec3; 4]) -> Self {
+        Self::new(
+            Vec4::new(v[0].x, v[1].x, v[2].x, v[3].x),
+            Vec4::new(v[0].y, v[1].y, v[2].y, v[3].y),
+            Vec4::new(v[0].z, v[1].z, v[2].z, v[3].z),
+        )
+    }
+
+    fn lanes(&self) -> [Vec3; 4] {
+        let [x, y, z] = self.v;
+        [0, 1, 2, 3].map(|lane| Vec3::new(x[lane], y[lane], z[lane]))
+    }
+}
+
+pub struct Position<S: Storage = TableStorage>(AoSoAVec3, PhantomData<S>);
+
+impl<S: Storage> Component for Position<S> {
+    type Storage = S;
+}
+
+pub struct Velocity<S: Storage = TableStorage>(AoSoAVec3, PhantomData<S>);
+
+impl<S: Storage> Component for Velocity<S> {
+    type Storage = S;
+}
+
+pub struct Benchmark<'w, S: Storage = TableStorage>(
+    World,
+    QueryState<(&'w Velocity<S>, &'w mut Position<S>)>,
+);
+
+//Bytes touched per virtual entity by the update and oscillator kernels
+pub fn traffic() -> Traffic {
+    Traffic::update::<Velocity, Position>(4)
 }
 
-#[derive(Component, Copy, Clone)]
-struct Position(AoSoAVec3);
+//Memory the world holds for this layout
+pub fn footprint(size: i32) -> Footprint {
+    Footprint::of(&Benchmark::new(size).0, 4, F32_PAYLOAD)
+}
 
-#[derive(Component, Copy, Clone)]
-struct Velocity(AoSoAVec3);
+//Spawning, inserting into or removing from `size` virtual entities of this layout, see lifecycle.rs
+pub fn lifecycle(size: i32, operation: Operation) -> Prepared {
+    lifecycle::prepare(
+        size as usize / 4,
+        operation,
+        moving_bundle::<TableStorage>,
+        position_bundle::<TableStorage>,
+        velocity_bundle::<TableStorage>,
+    )
+}
 
-pub struct Benchmark<'w>(World, QueryState<(&'w Velocity, &'w mut Position)>);
+fn moving_bundle<S: Storage>(
+    inputs: &InputDistribution,
+    rng: &mut ThreadRng,
+) -> (Position<S>, Velocity<S>) {
+    let (position,) = position_bundle(inputs, rng);
+    let (velocity,) = velocity_bundle(inputs, rng);
+    (position, velocity)
+}
+
+fn position_bundle<S: Storage>(inputs: &InputDistribution, rng: &mut ThreadRng) -> (Position<S>,) {
+    let [pxs, pys, pzs] = [0, 1, 2].map(|_| inputs.position4(rng));
+    (Position(AoSoAVec3::new(pxs, pys, pzs), PhantomData),)
+}
+
+fn velocity_bundle<S: Storage>(inputs: &InputDistribution, rng: &mut ThreadRng) -> (Velocity<S>,) {
+    let [vxs, vys, vzs] = [0, 1, 2].map(|_| inputs.velocity4(rng));
+    (Velocity(AoSoAVec3::new(vxs, vys, vzs), PhantomData),)
+}
 
 impl<'w> Benchmark<'w> {
     pub fn new(size: i32) -> Self {
+        Self::with_storage(size)
+    }
+
+    //The world spawned from `setup.inputs`, then passed through `setup.transform`
+    pub fn with_setup(size: i32, setup: &Setup) -> Self {
+        let mut bench = Self::spawn(size, &setup.inputs);
+        bench.0 = (setup.transform)(std::mem::take(&mut bench.0));
+        bench
+    }
+}
+
+impl<'w, S: Storage> Benchmark<'w, S> {
+    pub fn with_storage(size: i32) -> Self {
+        Self::spawn(size, &InputDistribution::default())
+    }
+
+    fn spawn(size: i32, inputs: &InputDistribution) -> Self {
         let size = size / 4; //4 virtual entities per entity
 
         let mut world = World::new();
 
         let mut rng = rand::thread_rng();
 
-        world.spawn_batch((0..size).map(|_| {
-            let pxs = Vec4::new(
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-            );
-            let pys = Vec4::new(
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-            );
-            let pzs = Vec4::new(
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-            );
-
-            let vxs = Vec4::new(
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-            );
-            let vys = Vec4::new(
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-            );
-            let vzs = Vec4::new(
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-            );
-
-            (
-                Position(AoSoAVec3::new(pxs, pys, pzs)),
-                Velocity(AoSoAVec3::new(vxs, vys, vzs)),
-            )
-        }));
+        world.spawn_batch((0..size).map(|_| moving_bundle::<S>(inputs, &mut rng)));
 
-        let query = world.query::<(&Velocity, &mut Position)>();
+        let query = world.query::<(&Velocity<S>, &mut Position<S>)>();
         Self(world, query)
     }
 
+    #[cfg_attr(feature = "audit", inline(never))]
     pub fn run(&mut self, time: f32) {
         self.1
             .for_each_mut(&mut self.0, |(velocity, mut position)| {
-                position.0.v[0] += time * velocity.0.v[0];
-                position.0.v[1] += time * velocity.0.v[1];
-                position.0.v[2] += time * velocity.0.v[2];
+                update(time, velocity, &mut position)
+            });
+    }
+
+    //Same as run, split over the ComputeTaskPool in tasks of `batch_size` entities
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_par(&mut self, time: f32, batch_size: usize) {
+        self.1
+            .par_for_each_mut(&mut self.0, batch_size, |(velocity, mut position)| {
+                update(time, velocity, &mut position)
+            });
+    }
+
+    //Same as run, but prefetches the blocks `distance` entities ahead
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_prefetch(&mut self, time: f32, distance: usize) {
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                prefetch(velocity, distance);
+                prefetch(&*position, distance);
+                update(time, velocity, &mut position)
+            });
+    }
+
+    //Same as run, but writes the results with non-temporal stores
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_stream(&mut self, time: f32) {
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                update_with(time, velocity, &mut position, stream)
             });
+        stream_fence();
+    }
+
+    //Transcendental workload, one virtual entity (lane) at a time through libm
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_oscillator_libm(&mut self, time: f32) {
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                let [pxs, pys, pzs] = &mut position.0.v;
+                let [vxs, vys, vzs] = &velocity.0.v;
+                for i in 0..4 {
+                    let p = Vec3::new(pxs[i], pys[i], pzs[i]);
+                    let v = Vec3::new(vxs[i], vys[i], vzs[i]);
+                    let n = oscillate_scalar(p, v, time);
+                    pxs[i] = n.x;
+                    pys[i] = n.y;
+                    pzs[i] = n.z;
+                }
+            });
+    }
+
+    //Transcendental workload, all 4 lanes at once
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_oscillator<M: LaneMath>(&mut self, time: f32) {
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                position.0.v = oscillate_lanes::<M>(position.0.v, velocity.0.v, time);
+            });
+    }
+}
+
+//The update kernel of run, run_par, run_prefetch and the system
+#[inline(always)]
+fn update<S: Storage>(time: f32, velocity: &Velocity<S>, position: &mut Position<S>) {
+    update_with(time, velocity, position, |dst, value| *dst = value)
+}
+
+//The update kernel, writing each axis back through `store`
+#[inline(always)]
+fn update_with<S: Storage>(
+    time: f32,
+    velocity: &Velocity<S>,
+    position: &mut Position<S>,
+    store: impl Fn(&mut Vec4, Vec4),
+) {
+    for (p, v) in position.0.v.iter_mut().zip(&velocity.0.v) {
+        store(p, *p + time * *v);
+    }
+}
+
+//The update kernel as a regular system
+fn update_system(time: Res<TimeStep>, mut query: Query<(&Velocity, &mut Position)>) {
+    let time = time.0;
+    query.for_each_mut(|(velocity, mut position)| update(time, velocity, &mut position));
+}
+
+pub fn system_benchmark(size: i32) -> SystemBenchmark {
+    SystemBenchmark::new(Benchmark::new(size).0, update_system)
+}
+
+impl Lanes<Position> for Velocity {
+    const LANES: usize = 4;
+
+    #[inline(always)]
+    fn update(time: f32, velocity: &Self, position: &mut Position) {
+        update(time, velocity, position)
+    }
+
+    #[inline(always)]
+    fn moving(&self) -> bool {
+        self.0.v.iter().any(|v| *v != Vec4::ZERO)
+    }
+
+    fn stop(&mut self, lane: usize) {
+        for axis in self.0.v.iter_mut() {
+            axis[lane] = 0.0;
+        }
+    }
+
+    #[inline(always)]
+    fn read(position: &Position) -> f32 {
+        (position.0.v[0] + position.0.v[1] + position.0.v[2]).dot(Vec4::ONE)
+    }
+}
+
+//Change detection benchmark: stationary virtual entities and a `Changed<Position>` consumer
+pub fn change_benchmark(
+    size: i32,
+    stationary_fraction: f64,
+) -> ChangeBenchmark<Position, Velocity> {
+    ChangeBenchmark::new(Benchmark::new(size).0, stationary_fraction)
+}
+
+type GatherParams = (
+    Query<'static, 'static, (&'static LaneParents<4>, &'static mut Position)>,
+    Query<'static, 'static, &'static Velocity>,
+);
+
+//Gather workload: every virtual entity inherits the velocity of a parent looked up with Query::get.
+//Each lane's parent can sit in a different block, so one block takes 4 lookups and a lane shuffle.
+pub struct GatherBenchmark(World, SystemState<GatherParams>);
+
+impl GatherBenchmark {
+    pub fn new(size: i32, order: ParentOrder) -> Self {
+        let mut world = Benchmark::new(size).0;
+        gather::assign_lane_parents::<Position, 4>(&mut world, order);
+
+        let state = SystemState::new(&mut world);
+        Self(world, state)
+    }
+
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run(&mut self, time: f32) {
+        let (mut children, velocities) = self.1.get_mut(&mut self.0);
+
+        children.for_each_mut(|(parents, mut position)| {
+            let mut v = [Vec4::ZERO; 3];
+            for (lane, &(parent, parent_lane)) in parents.0.iter().enumerate() {
+                let velocity = &velocities.get(parent).unwrap().0;
+                for (v, parent_v) in v.iter_mut().zip(&velocity.v) {
+                    v[lane] = parent_v[parent_lane];
+                }
+            }
+
+            position.0.v[0] += time * v[0];
+            position.0.v[1] += time * v[1];
+            position.0.v[2] += time * v[2];
+        });
+    }
+}
+
+//Conversion of a world of AoS entities (see vec3_aos.rs) to blocks of 4 lanes, and back
+impl<'w> Benchmark<'w> {
+    //Packs the AoS entities' positions and velocities into new block entities, 4 at a time in query
+    //order, and removes them from the AoS entities.  Those stay alive with anything else they carry,
+    //and the returned map says where each one's values went.
+    pub fn from_aos(aos: vec3_aos::Benchmark) -> (Self, LaneMap) {
+        let mut world = aos.into_world();
+
+        let sources: Vec<(Entity, Vec3, Vec3)> = world
+            .query::<(Entity, &vec3_aos::Position, &vec3_aos::Velocity)>()
+            .iter(&world)
+            .map(|(entity, position, velocity)| (entity, position.0, velocity.0))
+            .collect();
+
+        //Unused lanes of the last block are left at zero, so updating them does nothing
+        let blocks: Vec<Entity> = world
+            .spawn_batch(sources.chunks(4).map(|chunk| {
+                let mut positions = [Vec3::ZERO; 4];
+                let mut velocities = [Vec3::ZERO; 4];
+                for (lane, &(_, position, velocity)) in chunk.iter().enumerate() {
+                    positions[lane] = position;
+                    velocities[lane] = velocity;
+                }
+
+                (
+                    Position(AoSoAVec3::from_lanes(positions), PhantomData),
+                    Velocity(AoSoAVec3::from_lanes(velocities), PhantomData),
+                )
+            }))
+            .collect();
+
+        let mut lanes = LaneMap::with_capacity(sources.len());
+        for (&block, chunk) in blocks.iter().zip(sources.chunks(4)) {
+            for (lane, &(entity, _, _)) in chunk.iter().enumerate() {
+                world
+                    .entity_mut(entity)
+                    .remove_bundle::<(vec3_aos::Position, vec3_aos::Velocity)>();
+                lanes.insert(entity, block, lane);
+            }
+        }
+
+        let query = world.query::<(&Velocity, &mut Position)>();
+        (Self(world, query), lanes)
+    }
+
+    //Moves every lane back into the AoS entity `lanes` (the map from_aos returned) says it came from and
+    //despawns the blocks.  The AoS entities keep their ids throughout, so handles to them never went
+    //stale.
+    pub fn into_aos(mut self, lanes: LaneMap) -> vec3_aos::Benchmark<'w> {
+        let blocks: HashMap<Entity, ([Vec3; 4], [Vec3; 4])> = self
+            .0
+            .query::<(Entity, &Position, &Velocity)>()
+            .iter(&self.0)
+            .map(|(block, position, velocity)| (block, (position.0.lanes(), velocity.0.lanes())))
+            .collect();
+
+        for (entity, block, lane) in lanes.iter() {
+            let (positions, velocities) = &blocks[&block];
+            self.0.entity_mut(entity).insert_bundle((
+                vec3_aos::Position(positions[lane]),
+                vec3_aos::Velocity(velocities[lane]),
+            ));
+        }
+        for &block in blocks.keys() {
+            self.0.despawn(block);
+        }
+
+        vec3_aos::Benchmark::from_world(self.0)
+    }
+
+    //Whether a converted AoS entity still has a vec3_aos position or velocity in the packed world, for
+    //checking a conversion
+    pub fn has_aos_components(&self, entity: Entity) -> Option<bool> {
+        let entity = self.0.get_entity(entity)?;
+        Some(entity.contains::<vec3_aos::Position>() || entity.contains::<vec3_aos::Velocity>())
+    }
+
+    //Position of a converted AoS entity, found through its lane
+    pub fn position(&self, lanes: &LaneMap, entity: Entity) -> Option<Vec3> {
+        let (block, lane) = lanes.get(entity)?;
+        let position = self.0.get::<Position>(block)?;
+        Some(position.0.lanes()[lane])
     }
 }
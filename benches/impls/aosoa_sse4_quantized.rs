@@ -0,0 +1,307 @@
+use bevy::ecs::component::TableStorage;
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use std::marker::PhantomData;
+use std::mem::size_of;
+
+use super::changes::{ChangeBenchmark, Lanes};
+use super::footprint::Footprint;
+use super::inputs::InputDistribution;
+use super::lifecycle::{self, Operation, Prepared};
+use super::quantize::{max_error_time_step, ErrorStats, Quantization};
+use super::setup::Setup;
+use super::storage::Storage;
+use super::system::{SystemBenchmark, TimeStep};
+use super::traffic::Traffic;
+use super::transcendental::{oscillate_lanes, oscillate_scalar, LaneMath};
+
+//AoSoA layout with 4 lanes stored as 16 bit words (24 bytes per block instead of 48).
+//Lanes are widened to Vec4 for the math and narrowed again on write.
+#[derive(Copy, Clone)]
+struct QuantizedAoSoAVec3 {
+    v: [[u16; 4]; 3],
+}
+
+impl QuantizedAoSoAVec3 {
+    #[inline(always)]
+    fn encode<Q: Quantization>(x: Vec4, y: Vec4, z: Vec4) -> Self {
+        QuantizedAoSoAVec3 {
+            v: [Q::encode4(x), Q::encode4(y), Q::encode4(z)],
+        }
+    }
+
+    #[inline(always)]
+    fn decode<Q: Quantization>(&self) -> [Vec4; 3] {
+        self.v.map(Q::decode4)
+    }
+}
+
+pub struct Position<Q: Quantization, S: Storage = TableStorage>(
+    QuantizedAoSoAVec3,
+    PhantomData<(Q, S)>,
+);
+
+impl<Q: Quantization, S: Storage> Component for Position<Q, S> {
+    type Storage = S;
+}
+
+pub struct Velocity<Q: Quantization, S: Storage = TableStorage>(
+    QuantizedAoSoAVec3,
+    PhantomData<(Q, S)>,
+);
+
+impl<Q: Quantization, S: Storage> Component for Velocity<Q, S> {
+    type Storage = S;
+}
+
+pub struct Benchmark<'w, Q: Quantization, S: Storage = TableStorage>(
+    World,
+    QueryState<(&'w Velocity<Q, S>, &'w mut Position<Q, S>)>,
+);
+
+//Bytes touched per virtual entity by the update kernel
+pub fn traffic<Q: Quantization>() -> Traffic {
+    Traffic::update::<Velocity<Q>, Position<Q>>(4)
+}
+
+//Memory the world holds for this layout.  Each value is a single u16.
+pub fn footprint<Q: Quantization>(size: i32) -> Footprint {
+    Footprint::of(&Benchmark::<Q>::new(size).0, 4, 6 * size_of::<u16>())
+}
+
+//Spawning, inserting into or removing from `size` virtual entities of this layout, see lifecycle.rs
+pub fn lifecycle<Q: Quantization>(size: i32, operation: Operation) -> Prepared {
+    lifecycle::prepare(
+        size as usize / 4,
+        operation,
+        |inputs, rng| {
+            let ps = [0, 1, 2].map(|_| inputs.position4(rng));
+            let vs = [0, 1, 2].map(|_| inputs.velocity4(rng));
+            moving_bundle::<Q, TableStorage>(ps, vs)
+        },
+        |inputs, rng| position_bundle::<Q, TableStorage>([0, 1, 2].map(|_| inputs.position4(rng))),
+        |inputs, rng| velocity_bundle::<Q, TableStorage>([0, 1, 2].map(|_| inputs.velocity4(rng))),
+    )
+}
+
+fn moving_bundle<Q: Quantization, S: Storage>(
+    ps: [Vec4; 3],
+    vs: [Vec4; 3],
+) -> (Position<Q, S>, Velocity<Q, S>) {
+    let (position,) = position_bundle(ps);
+    let (velocity,) = velocity_bundle(vs);
+    (position, velocity)
+}
+
+fn position_bundle<Q: Quantization, S: Storage>([pxs, pys, pzs]: [Vec4; 3]) -> (Position<Q, S>,) {
+    (Position(
+        QuantizedAoSoAVec3::encode::<Q>(pxs, pys, pzs),
+        PhantomData,
+    ),)
+}
+
+fn velocity_bundle<Q: Quantization, S: Storage>([vxs, vys, vzs]: [Vec4; 3]) -> (Velocity<Q, S>,) {
+    (Velocity(
+        QuantizedAoSoAVec3::encode::<Q>(vxs, vys, vzs),
+        PhantomData,
+    ),)
+}
+
+impl<'w, Q: Quantization> Benchmark<'w, Q> {
+    pub fn new(size: i32) -> Self {
+        Self::with_storage(size)
+    }
+
+    //The world spawned from `setup.inputs`, then passed through `setup.transform`
+    pub fn with_setup(size: i32, setup: &Setup) -> Self {
+        let mut bench = Self::from_values(&random_values(size, &setup.inputs));
+        bench.0 = (setup.transform)(std::mem::take(&mut bench.0));
+        bench
+    }
+}
+
+impl<'w, Q: Quantization, S: Storage> Benchmark<'w, Q, S> {
+    pub fn with_storage(size: i32) -> Self {
+        Self::from_values(&random_values(size, &InputDistribution::default()))
+    }
+
+    fn from_values(values: &[([Vec4; 3], [Vec4; 3])]) -> Self {
+        let mut world = World::new();
+
+        world.spawn_batch(values.iter().map(|&(ps, vs)| moving_bundle::<Q, S>(ps, vs)));
+
+        let query = world.query::<(&Velocity<Q, S>, &mut Position<Q, S>)>();
+        Self(world, query)
+    }
+
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run(&mut self, time: f32) {
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                update(time, velocity, &mut position)
+            });
+    }
+
+    //Same as run, split over the ComputeTaskPool in tasks of `batch_size` entities
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_par(&mut self, time: f32, batch_size: usize) {
+        self.1
+            .par_for_each_mut(&mut self.0, batch_size, |(velocity, mut position)| {
+                update(time, velocity, &mut position)
+            });
+    }
+
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_nochange(&mut self, time: f32) {
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                update(time, velocity, position.bypass_change_detection())
+            });
+    }
+
+    //Transcendental workload, one virtual entity (lane) at a time through libm
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_oscillator_libm(&mut self, time: f32) {
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                let [mut pxs, mut pys, mut pzs] = position.0.decode::<Q>();
+                let [vxs, vys, vzs] = velocity.0.decode::<Q>();
+                for i in 0..4 {
+                    let p = Vec3::new(pxs[i], pys[i], pzs[i]);
+                    let v = Vec3::new(vxs[i], vys[i], vzs[i]);
+                    let n = oscillate_scalar(p, v, time);
+                    pxs[i] = n.x;
+                    pys[i] = n.y;
+                    pzs[i] = n.z;
+                }
+                position.0 = QuantizedAoSoAVec3::encode::<Q>(pxs, pys, pzs);
+            });
+    }
+
+    //Transcendental workload, all 4 lanes at once
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_oscillator<M: LaneMath>(&mut self, time: f32) {
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                let ps = position.0.decode::<Q>();
+                let vs = velocity.0.decode::<Q>();
+                let [nxs, nys, nzs] = oscillate_lanes::<M>(ps, vs, time);
+                position.0 = QuantizedAoSoAVec3::encode::<Q>(nxs, nys, nzs);
+            });
+    }
+}
+
+fn random_values(size: i32, inputs: &InputDistribution) -> Vec<([Vec4; 3], [Vec4; 3])> {
+    let size = size / 4; //4 virtual entities per entity
+
+    let mut rng = rand::thread_rng();
+
+    (0..size)
+        .map(|_| {
+            let ps = [0, 1, 2].map(|_| inputs.position4(&mut rng));
+            let vs = [0, 1, 2].map(|_| inputs.velocity4(&mut rng));
+            (ps, vs)
+        })
+        .collect()
+}
+
+//Runs the quantized layout and a plain f32 AoSoA reference over the same inputs for `frames` updates
+//and compares every lane of the resulting positions
+pub fn error_stats<Q: Quantization>(size: i32, frames: usize) -> ErrorStats {
+    let values = random_values(size, &InputDistribution::default());
+    let mut bench = Benchmark::<Q>::from_values(&values);
+    let mut reference = values;
+
+    let mut rng = rand::thread_rng();
+
+    //Kept small enough that no position leaves the snorm16 range
+    let max_time = max_error_time_step(frames);
+
+    for _ in 0..frames {
+        let time = rng.gen_range(0.0..=max_time);
+        bench.run(time);
+        for (p, v) in reference.iter_mut() {
+            for i in 0..3 {
+                p[i] += time * v[i];
+            }
+        }
+    }
+
+    //spawn_batch into a fresh World hands out entity indices in spawn order
+    let mut query = bench.0.query::<(Entity, &Position<Q>)>();
+    let results: Vec<(u32, [Vec4; 3])> = query
+        .iter(&bench.0)
+        .map(|(entity, position)| (entity.id(), position.0.decode::<Q>()))
+        .collect();
+
+    ErrorStats::from_pairs(results.into_iter().flat_map(|(id, quantized)| {
+        let expected = reference[id as usize].0;
+        (0..3).flat_map(move |i| {
+            expected[i]
+                .to_array()
+                .into_iter()
+                .zip(quantized[i].to_array())
+        })
+    }))
+}
+
+//The update kernel of run, run_par, run_nochange and the system
+#[inline(always)]
+fn update<Q: Quantization, S: Storage>(
+    time: f32,
+    velocity: &Velocity<Q, S>,
+    position: &mut Position<Q, S>,
+) {
+    let [pxs, pys, pzs] = position.0.decode::<Q>();
+    let [vxs, vys, vzs] = velocity.0.decode::<Q>();
+    position.0 =
+        QuantizedAoSoAVec3::encode::<Q>(pxs + time * vxs, pys + time * vys, pzs + time * vzs);
+}
+
+//The update kernel as a regular system
+fn update_system<Q: Quantization>(
+    time: Res<TimeStep>,
+    mut query: Query<(&Velocity<Q>, &mut Position<Q>)>,
+) {
+    let time = time.0;
+    query.for_each_mut(|(velocity, mut position)| update(time, velocity, &mut position));
+}
+
+pub fn system_benchmark<Q: Quantization>(size: i32) -> SystemBenchmark {
+    SystemBenchmark::new(Benchmark::<Q>::new(size).0, update_system::<Q>)
+}
+
+impl<Q: Quantization> Lanes<Position<Q>> for Velocity<Q> {
+    const LANES: usize = 4;
+
+    #[inline(always)]
+    fn update(time: f32, velocity: &Self, position: &mut Position<Q>) {
+        update(time, velocity, position)
+    }
+
+    #[inline(always)]
+    fn moving(&self) -> bool {
+        self.0.v.iter().any(|v| *v != [Q::encode(0.0); 4])
+    }
+
+    fn stop(&mut self, lane: usize) {
+        for axis in self.0.v.iter_mut() {
+            axis[lane] = Q::encode(0.0);
+        }
+    }
+
+    #[inline(always)]
+    fn read(position: &Position<Q>) -> f32 {
+        let [xs, ys, zs] = position.0.decode::<Q>();
+        (xs + ys + zs).dot(Vec4::ONE)
+    }
+}
+
+//Change detection benchmark: stationary virtual entities and a `Changed<Position>` consumer
+pub fn change_benchmark<Q: Quantization>(
+    size: i32,
+    stationary_fraction: f64,
+) -> ChangeBenchmark<Position<Q>, Velocity<Q>> {
+    ChangeBenchmark::new(Benchmark::<Q>::new(size).0, stationary_fraction)
+}
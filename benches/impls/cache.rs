@@ -0,0 +1,90 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+const CACHE_DIR: &str = "/sys/devices/system/cpu/cpu0/cache";
+
+//Working sets benchmarked around each cache level, as fractions of its capacity: comfortably
+//inside, exactly full and spilling into the next level
+pub const CACHE_FRACTIONS: [f64; 3] = [0.5, 1.0, 2.0];
+
+//Smallest size worth timing; also keeps the widest layouts from ending up with no entities
+const MIN_SIZE: i32 = 64;
+
+//One data or unified cache of cpu0
+#[derive(Debug, Clone)]
+pub struct CacheLevel {
+    pub level: u32,
+    pub data_only: bool,
+    pub bytes: usize,
+}
+
+impl fmt::Display for CacheLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let suffix = if self.data_only { "d" } else { "" };
+        write!(f, "L{}{} {} KiB", self.level, suffix, self.bytes / 1024)
+    }
+}
+
+//The data caches of cpu0, smallest first.  Empty where sysfs doesn't describe them (not Linux,
+//some VMs), in which case callers fall back to fixed sizes.
+pub fn levels() -> Vec<CacheLevel> {
+    let entries = match fs::read_dir(CACHE_DIR) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut levels: Vec<CacheLevel> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("index"))
+        .filter_map(|entry| read_level(&entry.path()))
+        .collect();
+
+    levels.sort_by_key(|level| level.level);
+    levels
+}
+
+fn read_level(index: &Path) -> Option<CacheLevel> {
+    let read = |name: &str| fs::read_to_string(index.join(name)).ok();
+
+    let kind = read("type")?;
+    let data_only = match kind.trim() {
+        "Data" => true,
+        "Unified" => false,
+        _ => return None,
+    };
+
+    Some(CacheLevel {
+        level: read("level")?.trim().parse().ok()?,
+        data_only,
+        bytes: parse_size(read("size")?.trim())?,
+    })
+}
+
+//sysfs sizes look like `48K` or `32768K`
+fn parse_size(size: &str) -> Option<usize> {
+    let (digits, scale) = match size.as_bytes().last()? {
+        b'K' => (&size[..size.len() - 1], 1024),
+        b'M' => (&size[..size.len() - 1], 1024 * 1024),
+        b'G' => (&size[..size.len() - 1], 1024 * 1024 * 1024),
+        _ => (size, 1),
+    };
+    digits.parse::<usize>().ok().map(|n| n * scale)
+}
+
+//Sizes, in virtual entities, putting a layout's working set at each fraction of each cache level
+pub fn sizes(levels: &[CacheLevel], bytes_per_element: f64) -> Vec<i32> {
+    let mut sizes: Vec<i32> = levels
+        .iter()
+        .flat_map(|level| {
+            CACHE_FRACTIONS
+                .iter()
+                .map(move |fraction| fraction * level.bytes as f64 / bytes_per_element)
+        })
+        .map(|size| (size as i32).max(MIN_SIZE))
+        .collect();
+
+    sizes.sort_unstable();
+    sizes.dedup();
+    sizes
+}
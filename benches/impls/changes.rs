@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+use criterion::black_box;
+use rand::prelude::*;
+
+//Fractions of virtual entities given a zero velocity by the change detection benchmarks.  Their
+//update skips entities that don't move, so only the rest are marked changed for a `Changed<Position>`
+//consumer.  In a layout packing several virtual entities per entity, one moving lane marks the whole
+//entity changed, so the consumer sees more than actually moved.
+pub const STATIONARY_FRACTIONS: [f64; 4] = [0.0, 0.5, 0.9, 0.99];
+
+//A layout's velocity component, as the change detection benchmarks drive it alongside its position `P`
+pub trait Lanes<P: Component>: Component {
+    //Virtual entities per entity
+    const LANES: usize;
+
+    //The layout's update kernel
+    fn update(time: f32, velocity: &Self, position: &mut P);
+
+    //Whether any of the entity's lanes has a nonzero velocity
+    fn moving(&self) -> bool;
+
+    //Gives one lane a zero velocity
+    fn stop(&mut self, lane: usize);
+
+    //Reads every lane of a position, for the consumer
+    fn read(position: &P) -> f32;
+}
+
+//Picks which of an entity's `lanes` lanes stop, each with probability `fraction`
+pub fn stationary_lanes(rng: &mut impl Rng, fraction: f64, lanes: usize) -> Vec<bool> {
+    (0..lanes)
+        .map(|_| fraction > 0.0 && rng.gen_bool(fraction.min(1.0)))
+        .collect()
+}
+
+//The layout's update kernel over the entities with a moving lane.  Only taking the Mut marks an entity
+//changed, so stationary entities are left alone.
+#[inline(always)]
+pub fn update_moving<'q, P: Component, V: Lanes<P>>(
+    query: &mut QueryState<(&'q V, &'q mut P)>,
+    world: &mut World,
+    time: f32,
+) {
+    query.for_each_mut(world, |(velocity, mut position)| {
+        if velocity.moving() {
+            V::update(time, velocity, &mut position);
+        }
+    });
+}
+
+//The update kernel with a fraction of the virtual entities stationary, optionally followed by a
+//downstream query over just the positions it changed
+pub struct ChangeBenchmark<P: Component, V: Lanes<P>> {
+    world: World,
+    query: QueryState<(&'static V, &'static mut P)>,
+    consumer: QueryState<&'static P, Changed<P>>,
+    moving: usize,
+}
+
+impl<P: Component, V: Lanes<P>> ChangeBenchmark<P, V> {
+    //Stops a `stationary_fraction` of the virtual entities in a layout's freshly spawned world
+    pub fn new(mut world: World, stationary_fraction: f64) -> Self {
+        let mut rng = rand::thread_rng();
+        let mut moving = 0;
+
+        let mut velocities = world.query::<&mut V>();
+        velocities.for_each_mut(&mut world, |mut velocity| {
+            let lanes = stationary_lanes(&mut rng, stationary_fraction, V::LANES);
+            for (lane, stationary) in lanes.into_iter().enumerate() {
+                if stationary {
+                    velocity.stop(lane);
+                } else {
+                    moving += 1;
+                }
+            }
+        });
+
+        //Spawning counts as a change, which the first frame shouldn't see
+        world.clear_trackers();
+
+        let query = world.query::<(&V, &mut P)>();
+        let consumer = world.query_filtered::<&P, Changed<P>>();
+        Self {
+            world,
+            query,
+            consumer,
+            moving,
+        }
+    }
+
+    //Virtual entities with a nonzero velocity
+    pub fn moving(&self) -> usize {
+        self.moving
+    }
+
+    //Moves the entities that have a velocity and, with `consume`, reads back the changed positions.
+    //Returns the virtual entities the consumer visited.
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run(&mut self, time: f32, consume: bool) -> usize {
+        update_moving(&mut self.query, &mut self.world, time);
+
+        let mut visited = 0;
+        if consume {
+            let mut sum = 0.0;
+            self.consumer.for_each(&self.world, |position| {
+                sum += V::read(position);
+                visited += V::LANES;
+            });
+            black_box(sum);
+        }
+
+        self.world.clear_trackers();
+        visited
+    }
+}
@@ -0,0 +1,136 @@
+use bevy::ecs::world::EntityMut;
+use bevy::prelude::*;
+
+//A component none of the kernels touch, standing in for everything else a game keeps on its moving
+//entities (render handles, AI state, ...).  INDEX makes each one a distinct column, BYTES its size.
+#[derive(Component)]
+struct Cold<const INDEX: usize, const BYTES: usize>([u8; BYTES]);
+
+//Declares `ColdSize`, one variant per cold component size
+macro_rules! cold_sizes {
+    ($($variant:ident = $bytes:literal),* $(,)?) => {
+        //Sizes of the cold components
+        #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+        pub enum ColdSize {
+            $($variant),*
+        }
+
+        impl ColdSize {
+            pub const ALL: &'static [ColdSize] = &[$(ColdSize::$variant),*];
+
+            pub fn bytes(self) -> usize {
+                match self {
+                    $(ColdSize::$variant => $bytes),*
+                }
+            }
+
+            fn insert(self, count: ColdCount, entity: &mut EntityMut) {
+                match self {
+                    $(ColdSize::$variant => count.insert::<$bytes>(entity)),*
+                }
+            }
+        }
+    };
+}
+
+//Declares `ColdCount`, one variant per line, each line listing the cold component indices it adds to
+//the line before.  Every line goes into the bundle as its own tuple, since tuple bundles stop at 15
+//components, so adding a count is one more line of at most 15 new indices.
+macro_rules! cold_counts {
+    ($($variant:ident: $($index:literal)*;)*) => {
+        cold_counts!(@accumulate [] []; $($variant: $($index)*;)*);
+    };
+    (@accumulate [$($declared:tt)*] [$($groups:tt)*]; $variant:ident: $($index:literal)*; $($rest:tt)*) => {
+        cold_counts!(
+            @accumulate [$($declared)* $variant [$($groups)* ($($index)*)]] [$($groups)* ($($index)*)];
+            $($rest)*
+        );
+    };
+    (@accumulate [$($variant:ident [$(($($index:literal)*))*])*] [$($groups:tt)*];) => {
+        //Cold components per entity swept by the cold benchmarks, on top of the plain layout
+        #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+        pub enum ColdCount {
+            $($variant),*
+        }
+
+        impl ColdCount {
+            pub const ALL: &'static [ColdCount] = &[$(ColdCount::$variant),*];
+
+            pub fn count(self) -> usize {
+                match self {
+                    $(ColdCount::$variant => [$($($index),*),*].len()),*
+                }
+            }
+
+            //Their data is nonzero so the pages are really written, not left mapped to the zero page
+            fn insert<const BYTES: usize>(self, entity: &mut EntityMut) {
+                match self {
+                    $(ColdCount::$variant => entity.insert_bundle((
+                        $(($(Cold::<$index, BYTES>([$index as u8 + 1; BYTES]),)*),)*
+                    ))),*
+                };
+            }
+        }
+    };
+}
+
+cold_sizes!(Bytes4 = 4, Bytes16 = 16, Bytes64 = 64, Bytes256 = 256);
+
+cold_counts! {
+    Four: 0 1 2 3;
+    Eight: 4 5 6 7;
+    Sixteen: 8 9 10 11 12 13 14 15;
+}
+
+impl ColdSize {
+    pub fn from_bytes(bytes: usize) -> Result<Self, String> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|size| size.bytes() == bytes)
+            .ok_or_else(|| {
+                format!(
+                    "no {} byte cold component, sizes are {:?}",
+                    bytes,
+                    Self::ALL
+                        .iter()
+                        .map(|size| size.bytes())
+                        .collect::<Vec<_>>()
+                )
+            })
+    }
+}
+
+impl ColdCount {
+    pub fn from_count(count: usize) -> Result<Self, String> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|cold| cold.count() == count)
+            .ok_or_else(|| {
+                format!(
+                    "no bundle of {} cold components, counts are {:?}",
+                    count,
+                    Self::ALL
+                        .iter()
+                        .map(|cold| cold.count())
+                        .collect::<Vec<_>>()
+                )
+            })
+    }
+}
+
+//Adds `count` cold components of `size` to every entity in the world.  They're inserted one entity at
+//a time after spawning, the way gameplay code grows entities, so the table's columns (hot and cold)
+//are all reallocated in lockstep instead of each being allocated once up front.  Each entity gets them
+//as one bundle, so it moves straight to its final archetype instead of leaving an empty archetype
+//behind per component.
+pub fn add_cold(mut world: World, count: ColdCount, size: ColdSize) -> World {
+    let entities: Vec<Entity> = world.query::<Entity>().iter(&world).collect();
+
+    for entity in entities {
+        size.insert(count, &mut world.entity_mut(entity));
+    }
+
+    world
+}
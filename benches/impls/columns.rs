@@ -0,0 +1,100 @@
+use std::cell::UnsafeCell;
+
+use bevy::ecs::component::{ComponentStorage, ComponentTicks, StorageType};
+use bevy::ecs::storage::{Column, TableId};
+use bevy::prelude::*;
+
+//Whole table columns for a pair of dense components, read one and written the other.  Bevy's own
+//queries hand out components one entity at a time, which is why the SoA layouts elsewhere pack 4 or
+//more "virtual entities" into each entity to have something to vectorize over.  With the columns the
+//kernel gets a `&[R]` and `&mut [W]` per table and every element can be its own entity.
+//
+//Implemented on the `QueryState<(&R, &mut W)>` a layout already holds, which registers the components,
+//validates the world and keeps its archetypes up to date.  Its matched table ids aren't public, so the
+//tables are picked by their columns, which for an unfiltered pair of table components is the set it
+//matched.  Both components must use table storage: sparse set components have no table column to slice.
+pub trait ColumnQuery<R: Component, W: Component> {
+    //Calls `func` with the read and written column of every matching table, then marks every row
+    //of the written columns changed, as writing through `Mut` would
+    fn for_each_column_mut(&mut self, world: &mut World, func: impl FnMut(&[R], &mut [W]));
+
+    //Same as for_each_column_mut, without touching the change ticks
+    fn for_each_column_mut_nochange(&mut self, world: &mut World, func: impl FnMut(&[R], &mut [W]));
+}
+
+impl<'w, R: Component, W: Component> ColumnQuery<R, W> for QueryState<(&'w R, &'w mut W)> {
+    fn for_each_column_mut(&mut self, world: &mut World, func: impl FnMut(&[R], &mut [W])) {
+        let change_tick = world.increment_change_tick();
+        for_each_column(self, world, func, |ticks| {
+            for tick in ticks {
+                //SAFETY: `world` is borrowed mutably, so nothing else can be looking at the ticks
+                unsafe { &mut *tick.get() }.set_changed(change_tick);
+            }
+        });
+    }
+
+    fn for_each_column_mut_nochange(
+        &mut self,
+        world: &mut World,
+        func: impl FnMut(&[R], &mut [W]),
+    ) {
+        for_each_column(self, world, func, |_| {});
+    }
+}
+
+fn for_each_column<R: Component, W: Component>(
+    state: &mut QueryState<(&R, &mut W)>,
+    world: &mut World,
+    mut func: impl FnMut(&[R], &mut [W]),
+    mut mark_changed: impl FnMut(&[UnsafeCell<ComponentTicks>]),
+) {
+    assert!(
+        uses_tables::<R>() && uses_tables::<W>(),
+        "column queries need table storage, sparse set components have no column"
+    );
+
+    //Panics if `world` isn't the one the query was created from
+    state.update_archetypes(world);
+
+    let (read, write) = (world.init_component::<R>(), world.init_component::<W>());
+    assert_ne!(read, write, "a column can't be both read and written");
+
+    let tables = &world.storages().tables;
+    for index in 0..tables.len() {
+        let table = &tables[TableId::new(index)];
+        if table.is_empty() || !(table.has_column(read) && table.has_column(write)) {
+            continue;
+        }
+
+        let read_column = table.get_column(read).unwrap();
+        let write_column = table.get_column(write).unwrap();
+
+        //SAFETY: the columns were matched by component id, so they hold R and W.  They are
+        //different components, so the slices don't alias, and `world` is borrowed mutably, so
+        //nothing else can access them while `func` runs.
+        let (read, write) =
+            unsafe { (read_slice::<R>(read_column), write_slice::<W>(write_column)) };
+        func(read, write);
+
+        mark_changed(write_column.get_ticks_slice());
+    }
+}
+
+fn uses_tables<T: Component>() -> bool {
+    matches!(
+        <T::Storage as ComponentStorage>::STORAGE_TYPE,
+        StorageType::Table
+    )
+}
+
+unsafe fn read_slice<T>(column: &Column) -> &[T] {
+    let cells = column.get_data_slice::<T>();
+    std::slice::from_raw_parts(cells.as_ptr() as *const T, cells.len())
+}
+
+//UnsafeCell<T> has the same layout as T, and is what makes writing through the shared column sound
+#[allow(clippy::mut_from_ref)]
+unsafe fn write_slice<T>(column: &Column) -> &mut [T] {
+    let cells = column.get_data_slice::<T>();
+    std::slice::from_raw_parts_mut(UnsafeCell::raw_get(cells.as_ptr()), cells.len())
+}
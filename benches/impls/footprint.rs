@@ -0,0 +1,193 @@
+use std::mem::size_of;
+
+use bevy::ecs::bundle::Bundle;
+use bevy::ecs::component::{ComponentTicks, StorageType, TableStorage};
+use bevy::ecs::storage::TableId;
+use bevy::math::Vec3A;
+use bevy::prelude::*;
+
+//Position and velocity, 3 f32 each: the bytes a virtual entity actually needs
+pub const F32_PAYLOAD: usize = 6 * size_of::<f32>();
+
+//The added and changed ticks Bevy stores interleaved next to every component
+pub const TICK_BYTES: usize = size_of::<ComponentTicks>();
+
+//Memory a layout's World holds for its components, read back from Bevy's storages rather than
+//declared by the layout
+#[derive(Debug, Clone)]
+pub struct Footprint {
+    pub virtual_entities: usize,
+    pub archetypes: usize,
+    pub tables: usize,
+    pub columns: Vec<ColumnFootprint>,
+    //Bytes of actual position/velocity data
+    pub payload_bytes: usize,
+}
+
+//One table column or sparse set
+#[derive(Debug, Clone)]
+pub struct ColumnFootprint {
+    pub component: String,
+    pub storage: StorageType,
+    pub size: usize,
+    pub align: usize,
+    pub rows: usize,
+}
+
+impl ColumnFootprint {
+    pub fn bytes(&self) -> usize {
+        self.size * self.rows
+    }
+
+    //Every row also carries its added/changed ticks
+    pub fn tick_bytes(&self) -> usize {
+        TICK_BYTES * self.rows
+    }
+}
+
+impl Footprint {
+    //`lanes` is the number of virtual entities per entity and `payload` the bytes each of them needs
+    pub fn of(world: &World, lanes: usize, payload: usize) -> Self {
+        let components = world.components();
+        let storages = world.storages();
+
+        let mut archetypes = 0;
+        let mut entities = 0;
+        let mut tables: Vec<TableId> = Vec::new();
+        let mut columns = Vec::new();
+
+        for archetype in world.archetypes().iter().filter(|a| !a.is_empty()) {
+            archetypes += 1;
+            entities += archetype.len();
+
+            //Archetypes can share a table; count its columns once
+            if !tables.contains(&archetype.table_id()) {
+                tables.push(archetype.table_id());
+
+                let rows = storages
+                    .tables
+                    .get(archetype.table_id())
+                    .map_or(0, |table| table.len());
+                columns.extend(
+                    archetype
+                        .table_components()
+                        .iter()
+                        .filter_map(|&id| components.get_info(id))
+                        .map(|info| ColumnFootprint {
+                            component: info.name().to_string(),
+                            storage: StorageType::Table,
+                            size: info.layout().size(),
+                            align: info.layout().align(),
+                            rows,
+                        }),
+                );
+            }
+        }
+
+        //Sparse sets are per component rather than per archetype
+        let mut sparse_components = Vec::new();
+        for archetype in world.archetypes().iter().filter(|a| !a.is_empty()) {
+            for &id in archetype.sparse_set_components() {
+                if !sparse_components.contains(&id) {
+                    sparse_components.push(id);
+                }
+            }
+        }
+        columns.extend(sparse_components.into_iter().filter_map(|id| {
+            let info = components.get_info(id)?;
+            Some(ColumnFootprint {
+                component: info.name().to_string(),
+                storage: StorageType::SparseSet,
+                size: info.layout().size(),
+                align: info.layout().align(),
+                rows: storages.sparse_sets.get(id).map_or(0, |set| set.len()),
+            })
+        }));
+
+        let virtual_entities = entities * lanes;
+        Self {
+            virtual_entities,
+            archetypes,
+            tables: tables.len(),
+            columns,
+            payload_bytes: virtual_entities * payload,
+        }
+    }
+
+    pub fn component_bytes(&self) -> usize {
+        self.columns.iter().map(ColumnFootprint::bytes).sum()
+    }
+
+    pub fn tick_bytes(&self) -> usize {
+        self.columns.iter().map(ColumnFootprint::tick_bytes).sum()
+    }
+
+    //Alignment padding and unused lanes: component bytes that aren't payload
+    pub fn padding_bytes(&self) -> usize {
+        self.component_bytes().saturating_sub(self.payload_bytes)
+    }
+
+    //Share of the component bytes that is padding, if there are any components
+    pub fn padding_fraction(&self) -> Option<f64> {
+        match self.component_bytes() {
+            0 => None,
+            bytes => Some(self.padding_bytes() as f64 / bytes as f64),
+        }
+    }
+
+    //None for a world without virtual entities, e.g. a size smaller than the layout's lanes
+    pub fn bytes_per_virtual_entity(&self) -> Option<f64> {
+        match self.virtual_entities {
+            0 => None,
+            entities => Some((self.component_bytes() + self.tick_bytes()) as f64 / entities as f64),
+        }
+    }
+}
+
+//The explosion layouts' modules (vec3, vec3_aligned, soa) don't build their worlds through a
+//footprint hook, so they're measured on worlds holding components of the same sizes: a Vec3 or Vec3A
+//position and velocity per entity, or one f32 per axis
+struct Position<T>(T);
+
+impl<T: Send + Sync + 'static> Component for Position<T> {
+    type Storage = TableStorage;
+}
+
+struct Velocity<T>(T);
+
+impl<T: Send + Sync + 'static> Component for Velocity<T> {
+    type Storage = TableStorage;
+}
+
+struct Axis<const N: usize>(f32);
+
+impl<const N: usize> Component for Axis<N> {
+    type Storage = TableStorage;
+}
+
+pub fn vec3(size: i32) -> Footprint {
+    stand_in(size, || (Position(Vec3::ZERO), Velocity(Vec3::ZERO)))
+}
+
+pub fn vec3_aligned(size: i32) -> Footprint {
+    stand_in(size, || (Position(Vec3A::ZERO), Velocity(Vec3A::ZERO)))
+}
+
+pub fn soa(size: i32) -> Footprint {
+    stand_in(size, || {
+        (
+            Axis::<0>(0.0),
+            Axis::<1>(0.0),
+            Axis::<2>(0.0),
+            Axis::<3>(0.0),
+            Axis::<4>(0.0),
+            Axis::<5>(0.0),
+        )
+    })
+}
+
+fn stand_in<B: Bundle>(size: i32, bundle: impl Fn() -> B) -> Footprint {
+    let mut world = World::new();
+    world.spawn_batch((0..size).map(|_| bundle()));
+    Footprint::of(&world, 1, F32_PAYLOAD)
+}
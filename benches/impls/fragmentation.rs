@@ -0,0 +1,54 @@
+use bevy::ecs::world::EntityMut;
+use bevy::prelude::*;
+
+//Zero sized marker components.  Every subset of them is its own archetype (and table), so tagging a
+//layout's entities with different subsets spreads one homogeneous world over many archetypes without
+//changing the data the kernels touch.
+#[derive(Component)]
+struct Marker<const BIT: usize>;
+
+const MARKERS: usize = 10;
+
+pub const MAX_ARCHETYPES: usize = 1 << MARKERS;
+
+//Archetype counts swept by the fragmentation benchmarks, from the spawn_batch best case to hundreds
+pub const ARCHETYPE_COUNTS: [usize; 6] = [1, 2, 8, 32, 128, 512];
+
+//Spreads the world's entities round robin over `archetypes` archetypes, entity i getting the markers
+//for the bits of i % archetypes.  Worlds with fewer entities than archetypes end up with one archetype
+//per entity.
+pub fn fragment(mut world: World, archetypes: usize) -> World {
+    assert!(
+        (1..=MAX_ARCHETYPES).contains(&archetypes),
+        "archetype count must be between 1 and {}",
+        MAX_ARCHETYPES
+    );
+
+    let entities: Vec<Entity> = world.query::<Entity>().iter(&world).collect();
+
+    for (i, entity) in entities.into_iter().enumerate() {
+        let combination = i % archetypes;
+        let mut entity = world.entity_mut(entity);
+        for bit in (0..MARKERS).filter(|bit| combination & (1 << bit) != 0) {
+            insert_marker(&mut entity, bit);
+        }
+    }
+
+    world
+}
+
+fn insert_marker(entity: &mut EntityMut, bit: usize) {
+    match bit {
+        0 => entity.insert(Marker::<0>),
+        1 => entity.insert(Marker::<1>),
+        2 => entity.insert(Marker::<2>),
+        3 => entity.insert(Marker::<3>),
+        4 => entity.insert(Marker::<4>),
+        5 => entity.insert(Marker::<5>),
+        6 => entity.insert(Marker::<6>),
+        7 => entity.insert(Marker::<7>),
+        8 => entity.insert(Marker::<8>),
+        9 => entity.insert(Marker::<9>),
+        _ => unreachable!("only {} markers", MARKERS),
+    };
+}
@@ -0,0 +1,154 @@
+use bevy::ecs::system::SystemState;
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use super::vec3_aos::{self, Position, Velocity};
+
+//Window, in virtual entities, clustered parents are drawn from around their child
+const CLUSTER_SIZE: usize = 64;
+
+//Where the parent each virtual entity inherits its velocity from sits relative to it
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ParentOrder {
+    //Uniformly random parents, so lookups miss the cache once the world outgrows it
+    Random,
+    //Random parents, with children iterated in increasing parent order so lookups only move forwards
+    Sorted,
+    //Parents within CLUSTER_SIZE virtual entities of their child, like a hierarchy spawned together
+    Clustered,
+    //Every virtual entity is its own parent.  Lookups walk the table in order, which leaves just the
+    //cost of going through Query::get.
+    Own,
+}
+
+impl ParentOrder {
+    pub fn name(self) -> &'static str {
+        match self {
+            ParentOrder::Random => "random",
+            ParentOrder::Sorted => "sorted",
+            ParentOrder::Clustered => "clustered",
+            ParentOrder::Own => "own",
+        }
+    }
+}
+
+//The parent of each of `size` virtual entities, as a virtual entity index
+pub fn parent_indices(size: usize, order: ParentOrder, rng: &mut impl Rng) -> Vec<usize> {
+    match order {
+        ParentOrder::Random => (0..size).map(|_| rng.gen_range(0..size)).collect(),
+        ParentOrder::Sorted => {
+            let mut parents = parent_indices(size, ParentOrder::Random, rng);
+            parents.sort_unstable();
+            parents
+        }
+        ParentOrder::Clustered => (0..size)
+            .map(|child| {
+                let start = child
+                    .saturating_sub(CLUSTER_SIZE / 2)
+                    .min(size.saturating_sub(CLUSTER_SIZE));
+                rng.gen_range(start..(start + CLUSTER_SIZE).min(size))
+            })
+            .collect(),
+        ParentOrder::Own => (0..size).collect(),
+    }
+}
+
+//Parent of each lane of a block: the block entity holding it and its lane within that block.  The
+//AoS layout is the one lane case.
+#[derive(Component)]
+pub struct LaneParents<const LANES: usize>(pub [(Entity, usize); LANES]);
+
+//Gives every lane of the world's blocks, the entities with a `B`, a parent in `order`.  Iteration
+//order is spawn order, so virtual entity i is lane i % LANES of block i / LANES.  Inserting moves
+//each block to the new table in spawn order, so that order is unchanged afterwards.
+pub fn assign_lane_parents<B: Component, const LANES: usize>(
+    world: &mut World,
+    order: ParentOrder,
+) {
+    let mut rng = rand::thread_rng();
+
+    let blocks: Vec<Entity> = world
+        .query_filtered::<Entity, With<B>>()
+        .iter(world)
+        .collect();
+
+    let parents = parent_indices(blocks.len() * LANES, order, &mut rng);
+    for (&block, lanes) in blocks.iter().zip(parents.chunks_exact(LANES)) {
+        let lanes = std::array::from_fn(|lane| (blocks[lanes[lane] / LANES], lanes[lane] % LANES));
+        world.entity_mut(block).insert(LaneParents(lanes));
+    }
+}
+
+//The children being moved and the velocities looked up through their parents, as a system would
+//declare them.  The two queries don't conflict, so both can be used at once.
+type GatherParams = (
+    Query<'static, 'static, (&'static LaneParents<1>, &'static mut Position)>,
+    Query<'static, 'static, &'static Velocity>,
+);
+
+//Gather workload on the plain AoS layout of vec3_aos.rs, each entity pointing at its parent
+pub struct Benchmark(World, SystemState<GatherParams>);
+
+impl Benchmark {
+    pub fn new(size: i32, order: ParentOrder) -> Self {
+        let mut world = vec3_aos::Benchmark::new(size).into_world();
+        assign_lane_parents::<Position, 1>(&mut world, order);
+
+        let state = SystemState::new(&mut world);
+        Self(world, state)
+    }
+
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run(&mut self, time: f32) {
+        let (mut children, velocities) = self.1.get_mut(&mut self.0);
+
+        children.for_each_mut(|(parents, mut position)| {
+            let [(parent, _)] = parents.0;
+            //Every entity has a Velocity, so the lookup can't fail
+            let velocity = velocities.get(parent).unwrap();
+            position.0 += time * velocity.0;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parents(size: usize, order: ParentOrder) -> Vec<usize> {
+        let parents = parent_indices(size, order, &mut StdRng::seed_from_u64(size as u64));
+        assert_eq!(parents.len(), size);
+        assert!(parents.iter().all(|&parent| parent < size));
+        parents
+    }
+
+    #[test]
+    fn clustered_parents_stay_near_their_child() {
+        for size in [0, 1, CLUSTER_SIZE / 2, CLUSTER_SIZE, CLUSTER_SIZE + 1, 1000] {
+            for (child, parent) in parents(size, ParentOrder::Clustered)
+                .into_iter()
+                .enumerate()
+            {
+                assert!(
+                    parent.abs_diff(child) < CLUSTER_SIZE,
+                    "parent {} of {} is outside the cluster",
+                    parent,
+                    child
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn sorted_parents_only_move_forwards() {
+        for size in [1, 1000] {
+            let parents = parents(size, ParentOrder::Sorted);
+            assert!(parents.windows(2).all(|pair| pair[0] <= pair[1]));
+        }
+    }
+
+    #[test]
+    fn own_parents_are_the_children() {
+        assert_eq!(parents(5, ParentOrder::Own), [0, 1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,68 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+
+//Velocities only use the low mantissa bits so that positions stay subnormal for a few thousand frames
+//instead of drifting into the normal range after the first couple of updates
+const SUBNORMAL_VELOCITY_BITS: u32 = 10;
+
+//Bound on the magnitude of every initial position and velocity component
+pub const INPUT_RANGE: f32 = 16.0;
+
+//Describes how the initial positions and velocities are drawn.  By default every component is uniform in
+//+/- INPUT_RANGE, which never produces subnormals.  With a nonzero `subnormal_fraction`, that fraction of
+//components is replaced by a random subnormal value instead, similar to what damping code produces.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct InputDistribution {
+    pub subnormal_fraction: f64,
+}
+
+impl InputDistribution {
+    pub fn with_subnormals(subnormal_fraction: f64) -> Self {
+        Self { subnormal_fraction }
+    }
+
+    pub fn position(&self, rng: &mut impl Rng) -> f32 {
+        self.sample(rng, f32::MANTISSA_DIGITS - 1)
+    }
+
+    pub fn velocity(&self, rng: &mut impl Rng) -> f32 {
+        self.sample(rng, SUBNORMAL_VELOCITY_BITS)
+    }
+
+    pub fn position3(&self, rng: &mut impl Rng) -> Vec3 {
+        Vec3::new(self.position(rng), self.position(rng), self.position(rng))
+    }
+
+    pub fn velocity3(&self, rng: &mut impl Rng) -> Vec3 {
+        Vec3::new(self.velocity(rng), self.velocity(rng), self.velocity(rng))
+    }
+
+    pub fn position4(&self, rng: &mut impl Rng) -> Vec4 {
+        Vec4::new(
+            self.position(rng),
+            self.position(rng),
+            self.position(rng),
+            self.position(rng),
+        )
+    }
+
+    pub fn velocity4(&self, rng: &mut impl Rng) -> Vec4 {
+        Vec4::new(
+            self.velocity(rng),
+            self.velocity(rng),
+            self.velocity(rng),
+            self.velocity(rng),
+        )
+    }
+
+    fn sample(&self, rng: &mut impl Rng, subnormal_bits: u32) -> f32 {
+        if self.subnormal_fraction > 0.0 && rng.gen_bool(self.subnormal_fraction.min(1.0)) {
+            //Zero exponent, nonzero mantissa
+            let mantissa = rng.gen_range(1..(1u32 << subnormal_bits));
+            let sign = if rng.gen() { 0x8000_0000 } else { 0 };
+            f32::from_bits(sign | mantissa)
+        } else {
+            rng.gen_range(-INPUT_RANGE..=INPUT_RANGE)
+        }
+    }
+}
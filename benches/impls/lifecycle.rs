@@ -0,0 +1,122 @@
+use bevy::ecs::bundle::Bundle;
+use bevy::ecs::system::CommandQueue;
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use super::inputs::InputDistribution;
+
+//Creating and reshaping each layout's entities, rather than iterating over them.  Layouts packing
+//several virtual entities per entity spawn fewer, bigger entities, and the simd_batch layouts insert
+//six components where the others insert two.
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Operation {
+    //World::spawn_batch with all the bundles at once
+    SpawnBatch,
+    //World::spawn, one entity at a time
+    Spawn,
+    //Commands::spawn_bundle for every entity, then applying the queue
+    Commands,
+    //Inserting the velocity components into every entity of a world spawned without them, as if
+    //they all started moving.  Each entity moves to another archetype.
+    Insert,
+    //Removing the velocity components from every entity, as if they all stopped
+    Remove,
+}
+
+impl Operation {
+    pub const ALL: [Operation; 5] = [
+        Operation::SpawnBatch,
+        Operation::Spawn,
+        Operation::Commands,
+        Operation::Insert,
+        Operation::Remove,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Operation::SpawnBatch => "spawn_batch",
+            Operation::Spawn => "spawn",
+            Operation::Commands => "commands",
+            Operation::Insert => "insert",
+            Operation::Remove => "remove",
+        }
+    }
+}
+
+//One run of an operation, with everything it needs already built.  Gives the world back so dropping
+//it isn't timed along with the operation.
+pub type Prepared = Box<dyn FnOnce() -> World>;
+
+//Builds the inputs of one run of `operation` on `count` entities, and for Insert and Remove the world
+//it modifies.  `moving` makes everything an entity of the layout is spawned with, `position` and
+//`velocity` the parts of that left after a Remove and added by an Insert.
+pub fn prepare<B: Bundle, P: Bundle, V: Bundle>(
+    count: usize,
+    operation: Operation,
+    moving: fn(&InputDistribution, &mut ThreadRng) -> B,
+    position: fn(&InputDistribution, &mut ThreadRng) -> P,
+    velocity: fn(&InputDistribution, &mut ThreadRng) -> V,
+) -> Prepared {
+    let inputs = InputDistribution::default();
+    let mut rng = rand::thread_rng();
+
+    let mut world = World::new();
+
+    match operation {
+        Operation::SpawnBatch => {
+            let bundles: Vec<B> = (0..count).map(|_| moving(&inputs, &mut rng)).collect();
+            Box::new(move || {
+                //Dropping the iterator spawns everything it hasn't yet
+                world.spawn_batch(bundles);
+                world
+            })
+        }
+        Operation::Spawn => {
+            let bundles: Vec<B> = (0..count).map(|_| moving(&inputs, &mut rng)).collect();
+            Box::new(move || {
+                for bundle in bundles {
+                    world.spawn().insert_bundle(bundle);
+                }
+                world
+            })
+        }
+        Operation::Commands => {
+            let bundles: Vec<B> = (0..count).map(|_| moving(&inputs, &mut rng)).collect();
+            Box::new(move || {
+                let mut queue = CommandQueue::default();
+                let mut commands = Commands::new(&mut queue, &world);
+                for bundle in bundles {
+                    commands.spawn_bundle(bundle);
+                }
+                queue.apply(&mut world);
+                world
+            })
+        }
+        Operation::Insert => {
+            //Spawned straight into the archetype a Remove leaves them in
+            let entities: Vec<Entity> = world
+                .spawn_batch((0..count).map(|_| position(&inputs, &mut rng)))
+                .collect();
+
+            let velocities: Vec<V> = (0..count).map(|_| velocity(&inputs, &mut rng)).collect();
+            Box::new(move || {
+                for (&entity, velocity) in entities.iter().zip(velocities) {
+                    world.entity_mut(entity).insert_bundle(velocity);
+                }
+                world
+            })
+        }
+        Operation::Remove => {
+            let entities: Vec<Entity> = world
+                .spawn_batch((0..count).map(|_| moving(&inputs, &mut rng)))
+                .collect();
+            Box::new(move || {
+                for &entity in &entities {
+                    world.entity_mut(entity).remove_bundle::<V>();
+                }
+                world
+            })
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+//Converting a vec3_aos world to a packed layout mid-game (for example for a physics burst) and back
+//afterwards.  The conversions live with the packed layout, see aosoa_sse4's from_aos and into_aos.
+
+//Where each AoS entity's values went when its world was converted to a packed layout: the block
+//entity holding them and the lane within it.  The AoS entities themselves stay alive, so handles
+//held by the rest of the game can still find their virtual entity, and converting back puts each
+//lane into the entity this map gives for it.
+#[derive(Default)]
+pub struct LaneMap(HashMap<Entity, (Entity, usize)>);
+
+impl LaneMap {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self(HashMap::with_capacity(capacity))
+    }
+
+    pub fn insert(&mut self, entity: Entity, block: Entity, lane: usize) {
+        self.0.insert(entity, (block, lane));
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<(Entity, usize)> {
+        self.0.get(&entity).copied()
+    }
+
+    //Every converted AoS entity with its block and lane
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, Entity, usize)> + '_ {
+        self.0
+            .iter()
+            .map(|(&entity, &(block, lane))| (entity, block, lane))
+    }
+}
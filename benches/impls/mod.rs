@@ -1,6 +1,32 @@
+pub mod aosoa_portable;
 pub mod aosoa_sse4;
+pub mod aosoa_sse4_quantized;
+pub mod cache;
+pub mod changes;
+pub mod cold;
+pub mod columns;
 pub mod vec3;
 pub mod vec3_aligned;
+pub mod vec3_aos;
+pub mod vec3_quantized;
+pub mod inputs;
+pub mod lifecycle;
+pub mod mxcsr;
 pub mod naive_batched_swizzle;
+pub mod footprint;
+pub mod fragmentation;
+pub mod gather;
+pub mod migration;
+pub mod parallel;
+pub mod prefetch;
+pub mod quantize;
+pub mod registry;
+pub mod setup;
+pub mod simd_batch_portable;
 pub mod simd_batch_sse4;
 pub mod soa;
+pub mod soa_columns;
+pub mod storage;
+pub mod system;
+pub mod traffic;
+pub mod transcendental;
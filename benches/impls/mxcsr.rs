@@ -0,0 +1,44 @@
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::{_mm_getcsr, _mm_setcsr};
+
+//MXCSR bits controlling subnormal handling in SSE/AVX arithmetic
+#[cfg(target_arch = "x86_64")]
+const FLUSH_TO_ZERO: u32 = 1 << 15;
+#[cfg(target_arch = "x86_64")]
+const DENORMALS_ARE_ZERO: u32 = 1 << 6;
+
+//Sets the flush-to-zero and denormals-are-zero bits for the current thread while alive and restores the
+//previous MXCSR on drop.  MXCSR is per thread, so this only covers kernels run on the calling thread.
+pub struct FlushDenormals {
+    #[cfg(target_arch = "x86_64")]
+    saved: u32,
+}
+
+impl FlushDenormals {
+    #[allow(deprecated)]
+    pub fn new(enabled: bool) -> Self {
+        #[cfg(target_arch = "x86_64")]
+        unsafe {
+            let saved = _mm_getcsr();
+            let bits = FLUSH_TO_ZERO | DENORMALS_ARE_ZERO;
+            _mm_setcsr(if enabled { saved | bits } else { saved & !bits });
+            Self { saved }
+        }
+
+        #[cfg(not(target_arch = "x86_64"))]
+        {
+            let _ = enabled;
+            Self {}
+        }
+    }
+}
+
+impl Drop for FlushDenormals {
+    #[allow(deprecated)]
+    fn drop(&mut self) {
+        #[cfg(target_arch = "x86_64")]
+        unsafe {
+            _mm_setcsr(self.saved);
+        }
+    }
+}
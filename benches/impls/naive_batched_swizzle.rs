@@ -4,109 +4,262 @@ use rand::prelude::*;
 
 use std::marker::PhantomData;
 
+use bevy::ecs::component::TableStorage;
 use bevy::prelude::*;
 
+use super::changes::{ChangeBenchmark, Lanes};
+use super::footprint::{Footprint, F32_PAYLOAD};
+use super::inputs::InputDistribution;
+use super::lifecycle::{self, Operation, Prepared};
+use super::setup::Setup;
+use super::storage::Storage;
+use super::system::{SystemBenchmark, TimeStep};
+use super::traffic::Traffic;
+use super::transcendental::{oscillate_lanes, oscillate_scalar, LaneMath};
+
 //"Batched" naive Vec3 requiring swizzling
 
-#[derive(Component, Copy, Clone)]
-struct Position([Vec3; 4]);
+pub struct Position<S: Storage = TableStorage>([Vec3; 4], PhantomData<S>);
+
+impl<S: Storage> Component for Position<S> {
+    type Storage = S;
+}
+
+pub struct Velocity<S: Storage = TableStorage>([Vec3; 4], PhantomData<S>);
+
+impl<S: Storage> Component for Velocity<S> {
+    type Storage = S;
+}
+
+pub struct Benchmark<'w, S: Storage = TableStorage>(
+    World,
+    QueryState<(&'w Velocity<S>, &'w mut Position<S>)>,
+);
+
+//Bytes touched per virtual entity by the update and oscillator kernels
+pub fn traffic() -> Traffic {
+    Traffic::update::<Velocity, Position>(4)
+}
+
+//Memory the world holds for this layout
+pub fn footprint(size: i32) -> Footprint {
+    Footprint::of(&Benchmark::new(size).0, 4, F32_PAYLOAD)
+}
 
-#[derive(Component, Copy, Clone)]
-struct Velocity([Vec3; 4]);
+//Spawning, inserting into or removing from `size` virtual entities of this layout, see lifecycle.rs
+pub fn lifecycle(size: i32, operation: Operation) -> Prepared {
+    lifecycle::prepare(
+        size as usize / 4,
+        operation,
+        moving_bundle::<TableStorage>,
+        position_bundle::<TableStorage>,
+        velocity_bundle::<TableStorage>,
+    )
+}
 
-pub struct Benchmark<'w>(World, QueryState<(&'w Velocity, &'w mut Position)>);
+fn moving_bundle<S: Storage>(
+    inputs: &InputDistribution,
+    rng: &mut ThreadRng,
+) -> (Position<S>, Velocity<S>) {
+    let (position,) = position_bundle(inputs, rng);
+    let (velocity,) = velocity_bundle(inputs, rng);
+    (position, velocity)
+}
+
+fn position_bundle<S: Storage>(inputs: &InputDistribution, rng: &mut ThreadRng) -> (Position<S>,) {
+    let ps = [0, 1, 2, 3].map(|_| inputs.position3(rng));
+    (Position(ps, PhantomData),)
+}
+
+fn velocity_bundle<S: Storage>(inputs: &InputDistribution, rng: &mut ThreadRng) -> (Velocity<S>,) {
+    let vs = [0, 1, 2, 3].map(|_| inputs.velocity3(rng));
+    (Velocity(vs, PhantomData),)
+}
 
 impl<'w> Benchmark<'w> {
     pub fn new(size: i32) -> Self {
+        Self::with_storage(size)
+    }
+
+    //The world spawned from `setup.inputs`, then passed through `setup.transform`
+    pub fn with_setup(size: i32, setup: &Setup) -> Self {
+        let mut bench = Self::spawn(size, &setup.inputs);
+        bench.0 = (setup.transform)(std::mem::take(&mut bench.0));
+        bench
+    }
+}
+
+impl<'w, S: Storage> Benchmark<'w, S> {
+    pub fn with_storage(size: i32) -> Self {
+        Self::spawn(size, &InputDistribution::default())
+    }
+
+    fn spawn(size: i32, inputs: &InputDistribution) -> Self {
         let size = size / 4; //4 virtual entities per entity
 
         let mut world = World::new();
 
         let mut rng = rand::thread_rng();
 
-        world.spawn_batch((0..size).map(|_| {
-            let ps = [0, 1, 2, 3].map(|x| {
-                let x1 = rng.gen_range(-16.0..=16.0);
-                let x2 = rng.gen_range(-16.0..=16.0);
-                let x3 = rng.gen_range(-16.0..=16.0);
+        world.spawn_batch((0..size).map(|_| moving_bundle::<S>(inputs, &mut rng)));
+
+        let query = world.query::<(&Velocity<S>, &mut Position<S>)>();
+        Self(world, query)
+    }
 
-                Vec3::new(x1, x2, x3)
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run(&mut self, time: f32) {
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                update(time, velocity, &mut position)
             });
 
-            let vs = [0, 1, 2, 3].map(|x| {
-                let x1 = rng.gen_range(-16.0..=16.0);
-                let x2 = rng.gen_range(-16.0..=16.0);
-                let x3 = rng.gen_range(-16.0..=16.0);
+        //NOTE: should use integer math to avoid -ffast-math complications?
+    }
 
-                Vec3::new(x1, x2, x3)
+    //Same as run, split over the ComputeTaskPool in tasks of `batch_size` entities
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_par(&mut self, time: f32, batch_size: usize) {
+        self.1
+            .par_for_each_mut(&mut self.0, batch_size, |(velocity, mut position)| {
+                update(time, velocity, &mut position)
             });
-            (Position(ps), Velocity(vs))
-        }));
+    }
 
-        let query = world.query::<(&Velocity, &mut Position)>();
-        Self(world, query)
+    //Transcendental workload, one virtual entity at a time through libm
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_oscillator_libm(&mut self, time: f32) {
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                for i in 0..4 {
+                    position.0[i] = oscillate_scalar(position.0[i], velocity.0[i], time);
+                }
+            });
     }
 
-    pub fn run(&mut self, time: f32) {
+    //Transcendental workload, swizzled into lanes like run
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_oscillator<M: LaneMath>(&mut self, time: f32) {
         self.1
             .for_each_mut(&mut self.0, |(velocity, mut position)| {
-                //Swizzle (hope it optimizes lol)
-
-                //NOTE: look at assembly to ensure benchmark is fair
-
-                let pxs = Vec4::new(
-                    position.0[0].x,
-                    position.0[1].x,
-                    position.0[2].x,
-                    position.0[3].x,
-                );
-                let pys = Vec4::new(
-                    position.0[0].y,
-                    position.0[1].y,
-                    position.0[2].y,
-                    position.0[3].y,
-                );
-                let pzs = Vec4::new(
-                    position.0[0].z,
-                    position.0[1].z,
-                    position.0[2].z,
-                    position.0[3].z,
-                );
-
-                let vxs = Vec4::new(
-                    velocity.0[0].x,
-                    velocity.0[1].x,
-                    velocity.0[2].x,
-                    velocity.0[3].x,
-                );
-                let vys = Vec4::new(
-                    velocity.0[0].y,
-                    velocity.0[1].y,
-                    velocity.0[2].y,
-                    velocity.0[3].y,
-                );
-                let vzs = Vec4::new(
-                    velocity.0[0].z,
-                    velocity.0[1].z,
-                    velocity.0[2].z,
-                    velocity.0[3].z,
-                );
-
-                //Do the vectorized math
-
-                let nxs = pxs + time * vxs;
-                let nys = pys + time * vys;
-                let nzs = pzs + time * vzs;
-
-                //Now re-arrange it back
+                let ps = to_lanes(&position.0);
+                let vs = to_lanes(&velocity.0);
+
+                let [nxs, nys, nzs] = oscillate_lanes::<M>(ps, vs, time);
 
                 for i in 0..4 {
-                    position.0[i].x = nxs[i];
-                    position.0[i].y = nys[i];
-                    position.0[i].z = nzs[i];
+                    position.0[i] = Vec3::new(nxs[i], nys[i], nzs[i]);
                 }
             });
+    }
+}
 
-        //NOTE: should use integer math to avoid -ffast-math complications?
+//The update kernel of run, run_par and the system
+#[inline(always)]
+fn update<S: Storage>(time: f32, velocity: &Velocity<S>, position: &mut Position<S>) {
+    //Swizzle (hope it optimizes lol)
+
+    //NOTE: look at assembly to ensure benchmark is fair
+
+    let pxs = Vec4::new(
+        position.0[0].x,
+        position.0[1].x,
+        position.0[2].x,
+        position.0[3].x,
+    );
+    let pys = Vec4::new(
+        position.0[0].y,
+        position.0[1].y,
+        position.0[2].y,
+        position.0[3].y,
+    );
+    let pzs = Vec4::new(
+        position.0[0].z,
+        position.0[1].z,
+        position.0[2].z,
+        position.0[3].z,
+    );
+
+    let vxs = Vec4::new(
+        velocity.0[0].x,
+        velocity.0[1].x,
+        velocity.0[2].x,
+        velocity.0[3].x,
+    );
+    let vys = Vec4::new(
+        velocity.0[0].y,
+        velocity.0[1].y,
+        velocity.0[2].y,
+        velocity.0[3].y,
+    );
+    let vzs = Vec4::new(
+        velocity.0[0].z,
+        velocity.0[1].z,
+        velocity.0[2].z,
+        velocity.0[3].z,
+    );
+
+    //Do the vectorized math
+
+    let nxs = pxs + time * vxs;
+    let nys = pys + time * vys;
+    let nzs = pzs + time * vzs;
+
+    //Now re-arrange it back
+
+    for i in 0..4 {
+        position.0[i].x = nxs[i];
+        position.0[i].y = nys[i];
+        position.0[i].z = nzs[i];
     }
 }
+
+#[inline(always)]
+fn to_lanes(v: &[Vec3; 4]) -> [Vec4; 3] {
+    [
+        Vec4::new(v[0].x, v[1].x, v[2].x, v[3].x),
+        Vec4::new(v[0].y, v[1].y, v[2].y, v[3].y),
+        Vec4::new(v[0].z, v[1].z, v[2].z, v[3].z),
+    ]
+}
+
+//The update kernel as a regular system
+fn update_system(time: Res<TimeStep>, mut query: Query<(&Velocity, &mut Position)>) {
+    let time = time.0;
+    query.for_each_mut(|(velocity, mut position)| update(time, velocity, &mut position));
+}
+
+pub fn system_benchmark(size: i32) -> SystemBenchmark {
+    SystemBenchmark::new(Benchmark::new(size).0, update_system)
+}
+
+impl Lanes<Position> for Velocity {
+    const LANES: usize = 4;
+
+    #[inline(always)]
+    fn update(time: f32, velocity: &Self, position: &mut Position) {
+        update(time, velocity, position)
+    }
+
+    #[inline(always)]
+    fn moving(&self) -> bool {
+        self.0.iter().any(|v| *v != Vec3::ZERO)
+    }
+
+    fn stop(&mut self, lane: usize) {
+        self.0[lane] = Vec3::ZERO;
+    }
+
+    #[inline(always)]
+    fn read(position: &Position) -> f32 {
+        (position.0[0] + position.0[1] + position.0[2] + position.0[3]).dot(Vec3::ONE)
+    }
+}
+
+//Change detection benchmark: stationary virtual entities and a `Changed<Position>` consumer
+pub fn change_benchmark(
+    size: i32,
+    stationary_fraction: f64,
+) -> ChangeBenchmark<Position, Velocity> {
+    ChangeBenchmark::new(Benchmark::new(size).0, stationary_fraction)
+}
@@ -0,0 +1,26 @@
+use bevy::tasks::{ComputeTaskPool, TaskPoolBuilder};
+
+//Thread count for the criterion parallel benchmarks, since the pool can't be rebuilt within a run
+pub const THREADS_VAR: &str = "SIMD_BENCH_THREADS";
+
+//Entities per task swept by the parallel benchmarks.  AoSoA entities already carry several virtual
+//entities each, so the best batch size differs per layout.
+pub const BATCH_SIZES: [usize; 6] = [16, 64, 256, 1024, 4096, 16384];
+
+//Builds the ComputeTaskPool that par_for_each_mut runs on, with `threads` threads or one per core.
+//Bevy keeps the pool in a global, so only the first call in a process decides the thread count.
+//Returns the thread count of the pool actually in use.
+pub fn init_task_pool(threads: Option<usize>) -> usize {
+    ComputeTaskPool::init(|| {
+        let mut builder = TaskPoolBuilder::new().thread_name("simd-bench compute".to_string());
+        if let Some(threads) = threads {
+            builder = builder.num_threads(threads);
+        }
+        builder.build()
+    })
+    .thread_num()
+}
+
+pub fn threads_from_env() -> Option<usize> {
+    std::env::var(THREADS_VAR).ok()?.parse().ok()
+}
@@ -0,0 +1,53 @@
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::{_mm_loadu_ps, _mm_prefetch, _mm_sfence, _mm_stream_ps, _MM_HINT_T0};
+use std::mem::{align_of, size_of};
+
+//Prefetch distances, in entities, swept by the prefetch benchmarks
+pub const PREFETCH_DISTANCES: [usize; 4] = [4, 8, 16, 32];
+
+//Components of a single archetype live contiguously in their table column, so the element `distance`
+//slots after the one currently being visited is the one for_each_mut will hand out `distance` steps later.
+//Prefetches never fault, so running off the end of the column is harmless.
+#[inline(always)]
+pub fn prefetch<T>(current: &T, distance: usize) {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        let ahead = (current as *const T).wrapping_add(distance);
+        _mm_prefetch::<_MM_HINT_T0>(ahead as *const i8);
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let _ = (current, distance);
+    }
+}
+
+//Writes `value` with non-temporal stores, bypassing the cache.  T must be made of whole 16 byte aligned
+//blocks of 4 f32s (Vec4, Simd<f32, 4/8/16>), as required by movntps.  Call `stream_fence` once the
+//kernel is done so the stores are ordered with later loads.
+#[inline(always)]
+pub fn stream<T: Copy>(dst: &mut T, value: T) {
+    assert!(size_of::<T>() % 16 == 0 && align_of::<T>() >= 16);
+
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        let src = &value as *const T as *const f32;
+        let dst = dst as *mut T as *mut f32;
+        for i in (0..size_of::<T>() / 4).step_by(4) {
+            _mm_stream_ps(dst.add(i), _mm_loadu_ps(src.add(i)));
+        }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        *dst = value;
+    }
+}
+
+#[inline(always)]
+pub fn stream_fence() {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        _mm_sfence();
+    }
+}
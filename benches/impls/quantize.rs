@@ -0,0 +1,152 @@
+use bevy::prelude::Vec4;
+
+use super::inputs::INPUT_RANGE;
+
+//Storage formats for the quantized layouts.  Components are stored as 16 bit words and are converted
+//to f32 lanes inside the kernel, then converted back on write.
+pub trait Quantization: Send + Sync + 'static {
+    const NAME: &'static str;
+
+    fn encode(x: f32) -> u16;
+    fn decode(x: u16) -> f32;
+
+    fn encode4(x: Vec4) -> [u16; 4] {
+        x.to_array().map(Self::encode)
+    }
+
+    fn decode4(x: [u16; 4]) -> Vec4 {
+        Vec4::from_array(x.map(Self::decode))
+    }
+}
+
+//IEEE 754 binary16, stored as raw bits
+pub struct F16;
+
+impl Quantization for F16 {
+    const NAME: &'static str = "f16";
+
+    #[inline(always)]
+    fn encode(x: f32) -> u16 {
+        half::f16::from_f32(x).to_bits()
+    }
+
+    #[inline(always)]
+    fn decode(x: u16) -> f32 {
+        half::f16::from_bits(x).to_f32()
+    }
+}
+
+//Values are clamped to +/- SNORM16_RANGE, so positions saturate once they drift far enough
+pub const SNORM16_RANGE: f32 = 64.0;
+
+//16 bit signed normalized integer, scaled by SNORM16_RANGE
+pub struct Snorm16;
+
+impl Quantization for Snorm16 {
+    const NAME: &'static str = "snorm16";
+
+    #[inline(always)]
+    fn encode(x: f32) -> u16 {
+        let x = (x / SNORM16_RANGE).clamp(-1.0, 1.0);
+        ((x * i16::MAX as f32).round() as i16) as u16
+    }
+
+    #[inline(always)]
+    fn decode(x: u16) -> f32 {
+        ((x as i16) as f32 / i16::MAX as f32).max(-1.0) * SNORM16_RANGE
+    }
+}
+
+//Largest time step for which `frames` updates keep every position inside +/- SNORM16_RANGE, so the
+//error against the f32 reference is rounding rather than clamping
+pub fn max_error_time_step(frames: usize) -> f32 {
+    (SNORM16_RANGE - INPUT_RANGE) / (INPUT_RANGE * frames as f32)
+}
+
+//Error of a quantized layout against an f32 reference run with the same inputs
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ErrorStats {
+    pub max_abs: f32,
+    pub mean_abs: f32,
+    pub rms: f32,
+}
+
+impl ErrorStats {
+    pub fn from_pairs(pairs: impl Iterator<Item = (f32, f32)>) -> Self {
+        let mut count = 0;
+        let mut max_abs: f32 = 0.0;
+        let mut sum_abs = 0.0;
+        let mut sum_squared = 0.0;
+
+        for (reference, quantized) in pairs {
+            let error = (reference - quantized).abs() as f64;
+            max_abs = max_abs.max(error as f32);
+            sum_abs += error;
+            sum_squared += error * error;
+            count += 1;
+        }
+
+        if count == 0 {
+            return Self::default();
+        }
+
+        Self {
+            max_abs,
+            mean_abs: (sum_abs / count as f64) as f32,
+            rms: (sum_squared / count as f64).sqrt() as f32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Every 1/64th across the snorm16 range and a little past it
+    fn sweep() -> impl Iterator<Item = f32> {
+        (-80 * 64..=80 * 64).map(|i| i as f32 / 64.0)
+    }
+
+    fn round_trip<Q: Quantization>(x: f32) -> f32 {
+        Q::decode(Q::encode(x))
+    }
+
+    #[test]
+    fn snorm16_rounds_to_half_a_step_inside_its_range() {
+        let half_step = 0.5 * SNORM16_RANGE / i16::MAX as f32;
+        //Scaling by the range isn't exact in f32
+        let slack = 2.0 * SNORM16_RANGE * f32::EPSILON;
+
+        for x in sweep().filter(|x| x.abs() <= SNORM16_RANGE) {
+            let error = (round_trip::<Snorm16>(x) - x).abs();
+            assert!(
+                error <= half_step + slack,
+                "{} came back off by {}",
+                x,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn snorm16_clamps_outside_its_range() {
+        for x in sweep().filter(|x| x.abs() > SNORM16_RANGE) {
+            assert_eq!(round_trip::<Snorm16>(x), SNORM16_RANGE.copysign(x));
+        }
+    }
+
+    #[test]
+    fn f16_rounds_to_half_an_epsilon() {
+        let half_epsilon = 0.5 * half::f16::EPSILON.to_f32();
+
+        for x in sweep() {
+            let error = (round_trip::<F16>(x) - x).abs();
+            assert!(
+                error <= half_epsilon * x.abs(),
+                "{} came back off by {}",
+                x,
+                error
+            );
+        }
+    }
+}
@@ -0,0 +1,1304 @@
+use bevy::ecs::component::SparseStorage;
+use bevy::prelude::Vec3;
+use rand::prelude::*;
+
+use super::footprint::{self, Footprint};
+use super::gather::{self, ParentOrder};
+use super::lifecycle::{Operation, Prepared};
+use super::quantize::{ErrorStats, Snorm16, F16};
+use super::setup::Setup;
+use super::traffic::Traffic;
+use super::transcendental::{self, Glam, Polynomial};
+use super::vec3::Explosion;
+use super::{
+    aosoa_portable, aosoa_sse4, aosoa_sse4_quantized, naive_batched_swizzle, simd_batch_portable,
+    simd_batch_sse4, soa, soa_columns, vec3, vec3_aos, vec3_quantized,
+};
+
+//One frame of a layout running a workload.  Draws its own inputs (time step, explosion, ...) from the rng.
+pub type Kernel = Box<dyn FnMut(&mut ThreadRng)>;
+
+//A layout × workload combination that can be built for any size, for running outside criterion
+pub struct Case {
+    pub layout: &'static str,
+    pub workload: &'static str,
+    pub build: fn(i32) -> Kernel,
+    //Builds the same kernel on a world spawned and reshaped by a Setup, for the layouts that support it
+    pub build_with_setup: Option<fn(i32, &Setup) -> Kernel>,
+    //Bytes touched per virtual entity, when the workload's memory traffic doesn't depend on the data
+    pub traffic: Option<fn() -> Traffic>,
+}
+
+impl Case {
+    pub fn name(&self) -> String {
+        format!("{}/{}", self.layout, self.workload)
+    }
+}
+
+//p += t * v: a mul and an add per axis
+const UPDATE_FLOPS: f64 = 6.0;
+
+//FLOPs per virtual entity for a workload, counting each transcendental as one.  Workloads sharing a
+//prefix run the same math, and the explosion's work depends on the data.
+pub fn flops_per_element(workload: &str) -> Option<f64> {
+    if workload.starts_with("update") {
+        Some(UPDATE_FLOPS)
+    } else if workload.starts_with("oscillator") {
+        Some(transcendental::OSCILLATOR_FLOPS)
+    } else {
+        None
+    }
+}
+
+pub fn generate_explosion(rng: &mut ThreadRng) -> Explosion {
+    Explosion {
+        center: Vec3::new(
+            rng.gen_range(0.0..=1.0),
+            rng.gen_range(0.0..=1.0),
+            rng.gen_range(0.0..=1.0),
+        ),
+        radius_squared: rng.gen_range(0.0..=1.0),
+    }
+}
+
+fn time(rng: &mut ThreadRng) -> f32 {
+    rng.gen_range(0.0..=1.0)
+}
+
+macro_rules! case {
+    ($layout:expr, $workload:expr, $bench:ty, $traffic:expr, |$b:ident, $rng:ident| $run:expr) => {
+        Case {
+            layout: $layout,
+            workload: $workload,
+            traffic: $traffic,
+            build_with_setup: None,
+            build: |size| {
+                let mut $b = <$bench>::new(size);
+                Box::new(move |$rng: &mut ThreadRng| {
+                    $run;
+                })
+            },
+        }
+    };
+}
+
+//A case whose layout can also build its world from a Setup, see setup.rs
+macro_rules! setup_case {
+    ($layout:expr, $workload:expr, $bench:ty, $traffic:expr, |$b:ident, $rng:ident| $run:expr) => {
+        Case {
+            build_with_setup: Some(|size, setup| {
+                let mut $b = <$bench>::with_setup(size, setup);
+                Box::new(move |$rng: &mut ThreadRng| {
+                    $run;
+                })
+            }),
+            ..case!($layout, $workload, $bench, $traffic, |$b, $rng| $run)
+        }
+    };
+}
+
+//The update kernel wrapped as a system and run through a Schedule
+macro_rules! system_case {
+    ($layout:expr, $system_benchmark:expr, $traffic:expr) => {
+        Case {
+            layout: $layout,
+            workload: "update_system",
+            traffic: $traffic,
+            build_with_setup: None,
+            build: |size| {
+                let mut b = $system_benchmark(size);
+                Box::new(move |rng: &mut ThreadRng| {
+                    b.run(time(rng));
+                })
+            },
+        }
+    };
+}
+
+//The update kernel with the layout's components stored in sparse sets instead of tables.  No traffic:
+//every component is looked up through the entity's sparse index, which the table byte counts leave out.
+macro_rules! sparse_case {
+    ($layout:expr, $bench:ty, |$b:ident, $rng:ident| $run:expr) => {
+        Case {
+            layout: $layout,
+            workload: "update_sparse",
+            traffic: None,
+            build_with_setup: None,
+            build: |size| {
+                let mut $b = <$bench>::with_storage(size);
+                Box::new(move |$rng: &mut ThreadRng| {
+                    $run;
+                })
+            },
+        }
+    };
+}
+
+//Every virtual entity inheriting its parent's velocity through Query::get
+macro_rules! gather_case {
+    ($layout:expr, $workload:expr, $bench:ty, $order:expr) => {
+        Case {
+            layout: $layout,
+            workload: $workload,
+            traffic: None,
+            build_with_setup: None,
+            build: |size| {
+                let mut b = <$bench>::new(size, $order);
+                Box::new(move |rng: &mut ThreadRng| {
+                    b.run(time(rng));
+                })
+            },
+        }
+    };
+}
+
+pub const CASES: &[Case] = &[
+    case!(
+        "vec3",
+        "explosion_nochangedetect",
+        vec3::Benchmark,
+        None,
+        |b, rng| b.run_nochange(generate_explosion(rng))
+    ),
+    case!(
+        "vec3_batch_4_swizzle",
+        "explosion_nochangedetect",
+        vec3::Benchmark,
+        None,
+        |b, rng| b.run_swizzle_nochange_4(generate_explosion(rng))
+    ),
+    case!(
+        "vec3_batch_8_swizzle",
+        "explosion_nochangedetect",
+        vec3::Benchmark,
+        None,
+        |b, rng| b.run_swizzle_nochange_8(generate_explosion(rng))
+    ),
+    case!(
+        "soa",
+        "explosion_nochangedetect",
+        soa::Benchmark,
+        None,
+        |b, rng| b.run_nochange(generate_explosion(rng))
+    ),
+    case!(
+        "soa_batch_4",
+        "explosion_nochangedetect",
+        soa::Benchmark,
+        None,
+        |b, rng| b.run_nochange_4(generate_explosion(rng))
+    ),
+    case!(
+        "soa_batch_8",
+        "explosion_nochangedetect",
+        soa::Benchmark,
+        None,
+        |b, rng| b.run_nochange_8(generate_explosion(rng))
+    ),
+    setup_case!(
+        "naive_batched_swizzle",
+        "update",
+        naive_batched_swizzle::Benchmark,
+        Some(|| naive_batched_swizzle::traffic().with_change_detection(1, 4)),
+        |b, rng| b.run(time(rng))
+    ),
+    case!(
+        "naive_batched_swizzle",
+        "oscillator_libm",
+        naive_batched_swizzle::Benchmark,
+        Some(|| naive_batched_swizzle::traffic().with_change_detection(1, 4)),
+        |b, rng| b.run_oscillator_libm(time(rng))
+    ),
+    case!(
+        "naive_batched_swizzle",
+        "oscillator_glam",
+        naive_batched_swizzle::Benchmark,
+        Some(|| naive_batched_swizzle::traffic().with_change_detection(1, 4)),
+        |b, rng| b.run_oscillator::<Glam>(time(rng))
+    ),
+    case!(
+        "naive_batched_swizzle",
+        "oscillator_polynomial",
+        naive_batched_swizzle::Benchmark,
+        Some(|| naive_batched_swizzle::traffic().with_change_detection(1, 4)),
+        |b, rng| b.run_oscillator::<Polynomial>(time(rng))
+    ),
+    setup_case!(
+        "aosoa_sse4",
+        "update",
+        aosoa_sse4::Benchmark,
+        Some(|| aosoa_sse4::traffic().with_change_detection(1, 4)),
+        |b, rng| b.run(time(rng))
+    ),
+    case!(
+        "aosoa_sse4",
+        "update_stream",
+        aosoa_sse4::Benchmark,
+        Some(|| aosoa_sse4::traffic().with_change_detection(1, 4)),
+        |b, rng| b.run_stream(time(rng))
+    ),
+    case!(
+        "aosoa_sse4",
+        "oscillator_libm",
+        aosoa_sse4::Benchmark,
+        Some(|| aosoa_sse4::traffic().with_change_detection(1, 4)),
+        |b, rng| b.run_oscillator_libm(time(rng))
+    ),
+    case!(
+        "aosoa_sse4",
+        "oscillator_glam",
+        aosoa_sse4::Benchmark,
+        Some(|| aosoa_sse4::traffic().with_change_detection(1, 4)),
+        |b, rng| b.run_oscillator::<Glam>(time(rng))
+    ),
+    case!(
+        "aosoa_sse4",
+        "oscillator_polynomial",
+        aosoa_sse4::Benchmark,
+        Some(|| aosoa_sse4::traffic().with_change_detection(1, 4)),
+        |b, rng| b.run_oscillator::<Polynomial>(time(rng))
+    ),
+    setup_case!(
+        "simd_batch_4_soa_simulated",
+        "update",
+        simd_batch_sse4::Benchmark,
+        Some(|| simd_batch_sse4::traffic().with_change_detection(3, 4)),
+        |b, rng| b.run_optimal(time(rng))
+    ),
+    setup_case!(
+        "simd_batch_4_soa_simulated",
+        "update_nochangedetect",
+        simd_batch_sse4::Benchmark,
+        Some(simd_batch_sse4::traffic),
+        |b, rng| b.run_optimal_nochange(time(rng))
+    ),
+    case!(
+        "simd_batch_4_soa_simulated",
+        "update_suboptimal",
+        simd_batch_sse4::Benchmark,
+        Some(|| simd_batch_sse4::traffic().with_change_detection(3, 4)),
+        |b, rng| b.run_suboptimal(time(rng))
+    ),
+    case!(
+        "simd_batch_4_soa_simulated",
+        "update_nochangedetect_stream",
+        simd_batch_sse4::Benchmark,
+        Some(simd_batch_sse4::traffic),
+        |b, rng| b.run_optimal_nochange_stream(time(rng))
+    ),
+    case!(
+        "simd_batch_4_soa_simulated",
+        "oscillator_libm",
+        simd_batch_sse4::Benchmark,
+        Some(|| simd_batch_sse4::traffic().with_change_detection(3, 4)),
+        |b, rng| b.run_oscillator_libm(time(rng))
+    ),
+    case!(
+        "simd_batch_4_soa_simulated",
+        "oscillator_glam",
+        simd_batch_sse4::Benchmark,
+        Some(|| simd_batch_sse4::traffic().with_change_detection(3, 4)),
+        |b, rng| b.run_oscillator::<Glam>(time(rng))
+    ),
+    case!(
+        "simd_batch_4_soa_simulated",
+        "oscillator_polynomial",
+        simd_batch_sse4::Benchmark,
+        Some(|| simd_batch_sse4::traffic().with_change_detection(3, 4)),
+        |b, rng| b.run_oscillator::<Polynomial>(time(rng))
+    ),
+    setup_case!(
+        "aosoa_portable_4",
+        "update",
+        aosoa_portable::Benchmark<4>,
+        Some(|| aosoa_portable::traffic::<4>().with_change_detection(1, 4)),
+        |b, rng| b.run(time(rng))
+    ),
+    setup_case!(
+        "aosoa_portable_8",
+        "update",
+        aosoa_portable::Benchmark<8>,
+        Some(|| aosoa_portable::traffic::<8>().with_change_detection(1, 8)),
+        |b, rng| b.run(time(rng))
+    ),
+    setup_case!(
+        "aosoa_portable_16",
+        "update",
+        aosoa_portable::Benchmark<16>,
+        Some(|| aosoa_portable::traffic::<16>().with_change_detection(1, 16)),
+        |b, rng| b.run(time(rng))
+    ),
+    setup_case!(
+        "simd_batch_4_soa_simulated_portable",
+        "update_nochangedetect",
+        simd_batch_portable::Benchmark<4>,
+        Some(simd_batch_portable::traffic::<4>),
+        |b, rng| b.run_optimal_nochange(time(rng))
+    ),
+    setup_case!(
+        "simd_batch_8_soa_simulated_portable",
+        "update_nochangedetect",
+        simd_batch_portable::Benchmark<8>,
+        Some(simd_batch_portable::traffic::<8>),
+        |b, rng| b.run_optimal_nochange(time(rng))
+    ),
+    setup_case!(
+        "simd_batch_16_soa_simulated_portable",
+        "update_nochangedetect",
+        simd_batch_portable::Benchmark<16>,
+        Some(simd_batch_portable::traffic::<16>),
+        |b, rng| b.run_optimal_nochange(time(rng))
+    ),
+    case!(
+        "aosoa_portable_4",
+        "update_stream",
+        aosoa_portable::Benchmark<4>,
+        Some(|| aosoa_portable::traffic::<4>().with_change_detection(1, 4)),
+        |b, rng| b.run_stream(time(rng))
+    ),
+    case!(
+        "aosoa_portable_8",
+        "update_stream",
+        aosoa_portable::Benchmark<8>,
+        Some(|| aosoa_portable::traffic::<8>().with_change_detection(1, 8)),
+        |b, rng| b.run_stream(time(rng))
+    ),
+    case!(
+        "aosoa_portable_16",
+        "update_stream",
+        aosoa_portable::Benchmark<16>,
+        Some(|| aosoa_portable::traffic::<16>().with_change_detection(1, 16)),
+        |b, rng| b.run_stream(time(rng))
+    ),
+    case!(
+        "simd_batch_4_soa_simulated_portable",
+        "update_nochangedetect_stream",
+        simd_batch_portable::Benchmark<4>,
+        Some(simd_batch_portable::traffic::<4>),
+        |b, rng| b.run_optimal_nochange_stream(time(rng))
+    ),
+    case!(
+        "simd_batch_8_soa_simulated_portable",
+        "update_nochangedetect_stream",
+        simd_batch_portable::Benchmark<8>,
+        Some(simd_batch_portable::traffic::<8>),
+        |b, rng| b.run_optimal_nochange_stream(time(rng))
+    ),
+    case!(
+        "simd_batch_16_soa_simulated_portable",
+        "update_nochangedetect_stream",
+        simd_batch_portable::Benchmark<16>,
+        Some(simd_batch_portable::traffic::<16>),
+        |b, rng| b.run_optimal_nochange_stream(time(rng))
+    ),
+    setup_case!(
+        "vec3_f16",
+        "update_nochangedetect",
+        vec3_quantized::Benchmark<F16>,
+        Some(vec3_quantized::traffic::<F16>),
+        |b, rng| b.run_nochange(time(rng))
+    ),
+    setup_case!(
+        "vec3_snorm16",
+        "update_nochangedetect",
+        vec3_quantized::Benchmark<Snorm16>,
+        Some(vec3_quantized::traffic::<Snorm16>),
+        |b, rng| b.run_nochange(time(rng))
+    ),
+    setup_case!(
+        "aosoa_sse4_f16",
+        "update_nochangedetect",
+        aosoa_sse4_quantized::Benchmark<F16>,
+        Some(aosoa_sse4_quantized::traffic::<F16>),
+        |b, rng| b.run_nochange(time(rng))
+    ),
+    setup_case!(
+        "aosoa_sse4_snorm16",
+        "update_nochangedetect",
+        aosoa_sse4_quantized::Benchmark<Snorm16>,
+        Some(aosoa_sse4_quantized::traffic::<Snorm16>),
+        |b, rng| b.run_nochange(time(rng))
+    ),
+    case!(
+        "aosoa_portable_4",
+        "oscillator_libm",
+        aosoa_portable::Benchmark<4>,
+        Some(|| aosoa_portable::traffic::<4>().with_change_detection(1, 4)),
+        |b, rng| b.run_oscillator_libm(time(rng))
+    ),
+    case!(
+        "aosoa_portable_4",
+        "oscillator_glam",
+        aosoa_portable::Benchmark<4>,
+        Some(|| aosoa_portable::traffic::<4>().with_change_detection(1, 4)),
+        |b, rng| b.run_oscillator::<Glam>(time(rng))
+    ),
+    case!(
+        "aosoa_portable_4",
+        "oscillator_polynomial",
+        aosoa_portable::Benchmark<4>,
+        Some(|| aosoa_portable::traffic::<4>().with_change_detection(1, 4)),
+        |b, rng| b.run_oscillator::<Polynomial>(time(rng))
+    ),
+    case!(
+        "aosoa_portable_8",
+        "oscillator_libm",
+        aosoa_portable::Benchmark<8>,
+        Some(|| aosoa_portable::traffic::<8>().with_change_detection(1, 8)),
+        |b, rng| b.run_oscillator_libm(time(rng))
+    ),
+    case!(
+        "aosoa_portable_8",
+        "oscillator_glam",
+        aosoa_portable::Benchmark<8>,
+        Some(|| aosoa_portable::traffic::<8>().with_change_detection(1, 8)),
+        |b, rng| b.run_oscillator::<Glam>(time(rng))
+    ),
+    case!(
+        "aosoa_portable_8",
+        "oscillator_polynomial",
+        aosoa_portable::Benchmark<8>,
+        Some(|| aosoa_portable::traffic::<8>().with_change_detection(1, 8)),
+        |b, rng| b.run_oscillator::<Polynomial>(time(rng))
+    ),
+    case!(
+        "aosoa_portable_16",
+        "oscillator_libm",
+        aosoa_portable::Benchmark<16>,
+        Some(|| aosoa_portable::traffic::<16>().with_change_detection(1, 16)),
+        |b, rng| b.run_oscillator_libm(time(rng))
+    ),
+    case!(
+        "aosoa_portable_16",
+        "oscillator_glam",
+        aosoa_portable::Benchmark<16>,
+        Some(|| aosoa_portable::traffic::<16>().with_change_detection(1, 16)),
+        |b, rng| b.run_oscillator::<Glam>(time(rng))
+    ),
+    case!(
+        "aosoa_portable_16",
+        "oscillator_polynomial",
+        aosoa_portable::Benchmark<16>,
+        Some(|| aosoa_portable::traffic::<16>().with_change_detection(1, 16)),
+        |b, rng| b.run_oscillator::<Polynomial>(time(rng))
+    ),
+    case!(
+        "simd_batch_4_soa_simulated_portable",
+        "oscillator_libm",
+        simd_batch_portable::Benchmark<4>,
+        Some(|| simd_batch_portable::traffic::<4>().with_change_detection(3, 4)),
+        |b, rng| b.run_oscillator_libm(time(rng))
+    ),
+    case!(
+        "simd_batch_4_soa_simulated_portable",
+        "oscillator_glam",
+        simd_batch_portable::Benchmark<4>,
+        Some(|| simd_batch_portable::traffic::<4>().with_change_detection(3, 4)),
+        |b, rng| b.run_oscillator::<Glam>(time(rng))
+    ),
+    case!(
+        "simd_batch_4_soa_simulated_portable",
+        "oscillator_polynomial",
+        simd_batch_portable::Benchmark<4>,
+        Some(|| simd_batch_portable::traffic::<4>().with_change_detection(3, 4)),
+        |b, rng| b.run_oscillator::<Polynomial>(time(rng))
+    ),
+    case!(
+        "simd_batch_8_soa_simulated_portable",
+        "oscillator_libm",
+        simd_batch_portable::Benchmark<8>,
+        Some(|| simd_batch_portable::traffic::<8>().with_change_detection(3, 8)),
+        |b, rng| b.run_oscillator_libm(time(rng))
+    ),
+    case!(
+        "simd_batch_8_soa_simulated_portable",
+        "oscillator_glam",
+        simd_batch_portable::Benchmark<8>,
+        Some(|| simd_batch_portable::traffic::<8>().with_change_detection(3, 8)),
+        |b, rng| b.run_oscillator::<Glam>(time(rng))
+    ),
+    case!(
+        "simd_batch_8_soa_simulated_portable",
+        "oscillator_polynomial",
+        simd_batch_portable::Benchmark<8>,
+        Some(|| simd_batch_portable::traffic::<8>().with_change_detection(3, 8)),
+        |b, rng| b.run_oscillator::<Polynomial>(time(rng))
+    ),
+    case!(
+        "simd_batch_16_soa_simulated_portable",
+        "oscillator_libm",
+        simd_batch_portable::Benchmark<16>,
+        Some(|| simd_batch_portable::traffic::<16>().with_change_detection(3, 16)),
+        |b, rng| b.run_oscillator_libm(time(rng))
+    ),
+    case!(
+        "simd_batch_16_soa_simulated_portable",
+        "oscillator_glam",
+        simd_batch_portable::Benchmark<16>,
+        Some(|| simd_batch_portable::traffic::<16>().with_change_detection(3, 16)),
+        |b, rng| b.run_oscillator::<Glam>(time(rng))
+    ),
+    case!(
+        "simd_batch_16_soa_simulated_portable",
+        "oscillator_polynomial",
+        simd_batch_portable::Benchmark<16>,
+        Some(|| simd_batch_portable::traffic::<16>().with_change_detection(3, 16)),
+        |b, rng| b.run_oscillator::<Polynomial>(time(rng))
+    ),
+    case!(
+        "vec3_f16",
+        "oscillator_libm",
+        vec3_quantized::Benchmark<F16>,
+        Some(|| vec3_quantized::traffic::<F16>().with_change_detection(1, 1)),
+        |b, rng| b.run_oscillator_libm(time(rng))
+    ),
+    case!(
+        "vec3_snorm16",
+        "oscillator_libm",
+        vec3_quantized::Benchmark<Snorm16>,
+        Some(|| vec3_quantized::traffic::<Snorm16>().with_change_detection(1, 1)),
+        |b, rng| b.run_oscillator_libm(time(rng))
+    ),
+    case!(
+        "aosoa_sse4_f16",
+        "oscillator_libm",
+        aosoa_sse4_quantized::Benchmark<F16>,
+        Some(|| aosoa_sse4_quantized::traffic::<F16>().with_change_detection(1, 4)),
+        |b, rng| b.run_oscillator_libm(time(rng))
+    ),
+    case!(
+        "aosoa_sse4_f16",
+        "oscillator_glam",
+        aosoa_sse4_quantized::Benchmark<F16>,
+        Some(|| aosoa_sse4_quantized::traffic::<F16>().with_change_detection(1, 4)),
+        |b, rng| b.run_oscillator::<Glam>(time(rng))
+    ),
+    case!(
+        "aosoa_sse4_f16",
+        "oscillator_polynomial",
+        aosoa_sse4_quantized::Benchmark<F16>,
+        Some(|| aosoa_sse4_quantized::traffic::<F16>().with_change_detection(1, 4)),
+        |b, rng| b.run_oscillator::<Polynomial>(time(rng))
+    ),
+    case!(
+        "aosoa_sse4_snorm16",
+        "oscillator_libm",
+        aosoa_sse4_quantized::Benchmark<Snorm16>,
+        Some(|| aosoa_sse4_quantized::traffic::<Snorm16>().with_change_detection(1, 4)),
+        |b, rng| b.run_oscillator_libm(time(rng))
+    ),
+    case!(
+        "aosoa_sse4_snorm16",
+        "oscillator_glam",
+        aosoa_sse4_quantized::Benchmark<Snorm16>,
+        Some(|| aosoa_sse4_quantized::traffic::<Snorm16>().with_change_detection(1, 4)),
+        |b, rng| b.run_oscillator::<Glam>(time(rng))
+    ),
+    case!(
+        "aosoa_sse4_snorm16",
+        "oscillator_polynomial",
+        aosoa_sse4_quantized::Benchmark<Snorm16>,
+        Some(|| aosoa_sse4_quantized::traffic::<Snorm16>().with_change_detection(1, 4)),
+        |b, rng| b.run_oscillator::<Polynomial>(time(rng))
+    ),
+    case!(
+        "vec3_aos",
+        "oscillator_libm",
+        vec3_aos::Benchmark,
+        Some(|| vec3_aos::traffic().with_change_detection(1, 1)),
+        |b, rng| b.run_oscillator_libm(time(rng))
+    ),
+    system_case!(
+        "naive_batched_swizzle",
+        naive_batched_swizzle::system_benchmark,
+        Some(|| naive_batched_swizzle::traffic().with_change_detection(1, 4))
+    ),
+    system_case!(
+        "aosoa_sse4",
+        aosoa_sse4::system_benchmark,
+        Some(|| aosoa_sse4::traffic().with_change_detection(1, 4))
+    ),
+    system_case!(
+        "simd_batch_4_soa_simulated",
+        simd_batch_sse4::system_benchmark,
+        Some(|| simd_batch_sse4::traffic().with_change_detection(3, 4))
+    ),
+    system_case!(
+        "aosoa_portable_4",
+        aosoa_portable::system_benchmark::<4>,
+        Some(|| aosoa_portable::traffic::<4>().with_change_detection(1, 4))
+    ),
+    system_case!(
+        "aosoa_portable_8",
+        aosoa_portable::system_benchmark::<8>,
+        Some(|| aosoa_portable::traffic::<8>().with_change_detection(1, 8))
+    ),
+    system_case!(
+        "aosoa_portable_16",
+        aosoa_portable::system_benchmark::<16>,
+        Some(|| aosoa_portable::traffic::<16>().with_change_detection(1, 16))
+    ),
+    system_case!(
+        "simd_batch_4_soa_simulated_portable",
+        simd_batch_portable::system_benchmark::<4>,
+        Some(|| simd_batch_portable::traffic::<4>().with_change_detection(3, 4))
+    ),
+    system_case!(
+        "simd_batch_8_soa_simulated_portable",
+        simd_batch_portable::system_benchmark::<8>,
+        Some(|| simd_batch_portable::traffic::<8>().with_change_detection(3, 8))
+    ),
+    system_case!(
+        "simd_batch_16_soa_simulated_portable",
+        simd_batch_portable::system_benchmark::<16>,
+        Some(|| simd_batch_portable::traffic::<16>().with_change_detection(3, 16))
+    ),
+    system_case!(
+        "vec3_f16",
+        vec3_quantized::system_benchmark::<F16>,
+        Some(|| vec3_quantized::traffic::<F16>().with_change_detection(1, 1))
+    ),
+    system_case!(
+        "vec3_snorm16",
+        vec3_quantized::system_benchmark::<Snorm16>,
+        Some(|| vec3_quantized::traffic::<Snorm16>().with_change_detection(1, 1))
+    ),
+    system_case!(
+        "aosoa_sse4_f16",
+        aosoa_sse4_quantized::system_benchmark::<F16>,
+        Some(|| aosoa_sse4_quantized::traffic::<F16>().with_change_detection(1, 4))
+    ),
+    system_case!(
+        "aosoa_sse4_snorm16",
+        aosoa_sse4_quantized::system_benchmark::<Snorm16>,
+        Some(|| aosoa_sse4_quantized::traffic::<Snorm16>().with_change_detection(1, 4))
+    ),
+    sparse_case!(
+        "naive_batched_swizzle",
+        naive_batched_swizzle::Benchmark<SparseStorage>,
+        |b, rng| b.run(time(rng))
+    ),
+    sparse_case!(
+        "aosoa_sse4",
+        aosoa_sse4::Benchmark<SparseStorage>,
+        |b, rng| b.run(time(rng))
+    ),
+    sparse_case!(
+        "simd_batch_4_soa_simulated",
+        simd_batch_sse4::Benchmark<SparseStorage>,
+        |b, rng| b.run_optimal(time(rng))
+    ),
+    sparse_case!(
+        "aosoa_portable_4",
+        aosoa_portable::Benchmark<4, SparseStorage>,
+        |b, rng| b.run(time(rng))
+    ),
+    sparse_case!(
+        "aosoa_portable_8",
+        aosoa_portable::Benchmark<8, SparseStorage>,
+        |b, rng| b.run(time(rng))
+    ),
+    sparse_case!(
+        "aosoa_portable_16",
+        aosoa_portable::Benchmark<16, SparseStorage>,
+        |b, rng| b.run(time(rng))
+    ),
+    sparse_case!(
+        "simd_batch_4_soa_simulated_portable",
+        simd_batch_portable::Benchmark<4, SparseStorage>,
+        |b, rng| b.run_optimal(time(rng))
+    ),
+    sparse_case!(
+        "simd_batch_8_soa_simulated_portable",
+        simd_batch_portable::Benchmark<8, SparseStorage>,
+        |b, rng| b.run_optimal(time(rng))
+    ),
+    sparse_case!(
+        "simd_batch_16_soa_simulated_portable",
+        simd_batch_portable::Benchmark<16, SparseStorage>,
+        |b, rng| b.run_optimal(time(rng))
+    ),
+    sparse_case!(
+        "vec3_f16",
+        vec3_quantized::Benchmark<F16, SparseStorage>,
+        |b, rng| b.run(time(rng))
+    ),
+    sparse_case!(
+        "vec3_snorm16",
+        vec3_quantized::Benchmark<Snorm16, SparseStorage>,
+        |b, rng| b.run(time(rng))
+    ),
+    sparse_case!(
+        "aosoa_sse4_f16",
+        aosoa_sse4_quantized::Benchmark<F16, SparseStorage>,
+        |b, rng| b.run(time(rng))
+    ),
+    sparse_case!(
+        "aosoa_sse4_snorm16",
+        aosoa_sse4_quantized::Benchmark<Snorm16, SparseStorage>,
+        |b, rng| b.run(time(rng))
+    ),
+    case!(
+        "soa_columns",
+        "update",
+        soa_columns::Benchmark,
+        Some(|| soa_columns::traffic().with_change_detection(3, 1)),
+        |b, rng| b.run(time(rng))
+    ),
+    case!(
+        "soa_columns",
+        "update_nochangedetect",
+        soa_columns::Benchmark,
+        Some(soa_columns::traffic),
+        |b, rng| b.run_nochange(time(rng))
+    ),
+    case!(
+        "soa_columns",
+        "update_query",
+        soa_columns::Benchmark,
+        Some(|| soa_columns::traffic().with_change_detection(3, 1)),
+        |b, rng| b.run_query(time(rng))
+    ),
+    case!(
+        "vec3_aos",
+        "update",
+        vec3_aos::Benchmark,
+        Some(|| vec3_aos::traffic().with_change_detection(1, 1)),
+        |b, rng| b.run(time(rng))
+    ),
+    gather_case!(
+        "vec3_aos",
+        "gather_random",
+        gather::Benchmark,
+        ParentOrder::Random
+    ),
+    gather_case!(
+        "vec3_aos",
+        "gather_sorted",
+        gather::Benchmark,
+        ParentOrder::Sorted
+    ),
+    gather_case!(
+        "vec3_aos",
+        "gather_clustered",
+        gather::Benchmark,
+        ParentOrder::Clustered
+    ),
+    gather_case!(
+        "vec3_aos",
+        "gather_own",
+        gather::Benchmark,
+        ParentOrder::Own
+    ),
+    gather_case!(
+        "aosoa_sse4",
+        "gather_random",
+        aosoa_sse4::GatherBenchmark,
+        ParentOrder::Random
+    ),
+    gather_case!(
+        "aosoa_sse4",
+        "gather_sorted",
+        aosoa_sse4::GatherBenchmark,
+        ParentOrder::Sorted
+    ),
+    gather_case!(
+        "aosoa_sse4",
+        "gather_clustered",
+        aosoa_sse4::GatherBenchmark,
+        ParentOrder::Clustered
+    ),
+    gather_case!(
+        "aosoa_sse4",
+        "gather_own",
+        aosoa_sse4::GatherBenchmark,
+        ParentOrder::Own
+    ),
+    gather_case!(
+        "simd_batch_4_soa_simulated",
+        "gather_random",
+        simd_batch_sse4::GatherBenchmark,
+        ParentOrder::Random
+    ),
+    gather_case!(
+        "simd_batch_4_soa_simulated",
+        "gather_sorted",
+        simd_batch_sse4::GatherBenchmark,
+        ParentOrder::Sorted
+    ),
+    gather_case!(
+        "simd_batch_4_soa_simulated",
+        "gather_clustered",
+        simd_batch_sse4::GatherBenchmark,
+        ParentOrder::Clustered
+    ),
+    gather_case!(
+        "simd_batch_4_soa_simulated",
+        "gather_own",
+        simd_batch_sse4::GatherBenchmark,
+        ParentOrder::Own
+    ),
+    gather_case!(
+        "aosoa_portable_4",
+        "gather_random",
+        aosoa_portable::GatherBenchmark<4>,
+        ParentOrder::Random
+    ),
+    gather_case!(
+        "aosoa_portable_4",
+        "gather_sorted",
+        aosoa_portable::GatherBenchmark<4>,
+        ParentOrder::Sorted
+    ),
+    gather_case!(
+        "aosoa_portable_4",
+        "gather_clustered",
+        aosoa_portable::GatherBenchmark<4>,
+        ParentOrder::Clustered
+    ),
+    gather_case!(
+        "aosoa_portable_4",
+        "gather_own",
+        aosoa_portable::GatherBenchmark<4>,
+        ParentOrder::Own
+    ),
+    gather_case!(
+        "aosoa_portable_8",
+        "gather_random",
+        aosoa_portable::GatherBenchmark<8>,
+        ParentOrder::Random
+    ),
+    gather_case!(
+        "aosoa_portable_8",
+        "gather_sorted",
+        aosoa_portable::GatherBenchmark<8>,
+        ParentOrder::Sorted
+    ),
+    gather_case!(
+        "aosoa_portable_8",
+        "gather_clustered",
+        aosoa_portable::GatherBenchmark<8>,
+        ParentOrder::Clustered
+    ),
+    gather_case!(
+        "aosoa_portable_8",
+        "gather_own",
+        aosoa_portable::GatherBenchmark<8>,
+        ParentOrder::Own
+    ),
+    gather_case!(
+        "aosoa_portable_16",
+        "gather_random",
+        aosoa_portable::GatherBenchmark<16>,
+        ParentOrder::Random
+    ),
+    gather_case!(
+        "aosoa_portable_16",
+        "gather_sorted",
+        aosoa_portable::GatherBenchmark<16>,
+        ParentOrder::Sorted
+    ),
+    gather_case!(
+        "aosoa_portable_16",
+        "gather_clustered",
+        aosoa_portable::GatherBenchmark<16>,
+        ParentOrder::Clustered
+    ),
+    gather_case!(
+        "aosoa_portable_16",
+        "gather_own",
+        aosoa_portable::GatherBenchmark<16>,
+        ParentOrder::Own
+    ),
+];
+
+//One frame of a layout's parallel update, split into tasks of the given number of entities
+pub type ParallelKernel = Box<dyn FnMut(&mut ThreadRng, usize)>;
+
+//A layout's update kernel run with par_for_each_mut on the ComputeTaskPool
+pub struct ParallelCase {
+    pub layout: &'static str,
+    //Virtual entities per entity, to relate the batch size to the element count
+    pub lanes: usize,
+    pub build: fn(i32) -> ParallelKernel,
+}
+
+macro_rules! parallel_case {
+    ($layout:expr, $bench:ty, $lanes:expr, |$b:ident, $rng:ident, $batch:ident| $run:expr) => {
+        ParallelCase {
+            layout: $layout,
+            lanes: $lanes,
+            build: |size| {
+                let mut $b = <$bench>::new(size);
+                Box::new(move |$rng: &mut ThreadRng, $batch: usize| {
+                    $run;
+                })
+            },
+        }
+    };
+}
+
+pub const PARALLEL_CASES: &[ParallelCase] = &[
+    parallel_case!(
+        "naive_batched_swizzle",
+        naive_batched_swizzle::Benchmark,
+        4,
+        |b, rng, batch| b.run_par(time(rng), batch)
+    ),
+    parallel_case!(
+        "aosoa_sse4",
+        aosoa_sse4::Benchmark,
+        4,
+        |b, rng, batch| b.run_par(time(rng), batch)
+    ),
+    parallel_case!(
+        "simd_batch_4_soa_simulated",
+        simd_batch_sse4::Benchmark,
+        4,
+        |b, rng, batch| b.run_optimal_par(time(rng), batch)
+    ),
+    parallel_case!(
+        "aosoa_portable_4",
+        aosoa_portable::Benchmark<4>,
+        4,
+        |b, rng, batch| b.run_par(time(rng), batch)
+    ),
+    parallel_case!(
+        "aosoa_portable_8",
+        aosoa_portable::Benchmark<8>,
+        8,
+        |b, rng, batch| b.run_par(time(rng), batch)
+    ),
+    parallel_case!(
+        "aosoa_portable_16",
+        aosoa_portable::Benchmark<16>,
+        16,
+        |b, rng, batch| b.run_par(time(rng), batch)
+    ),
+    parallel_case!(
+        "simd_batch_4_soa_simulated_portable",
+        simd_batch_portable::Benchmark<4>,
+        4,
+        |b, rng, batch| b.run_optimal_par(time(rng), batch)
+    ),
+    parallel_case!(
+        "simd_batch_8_soa_simulated_portable",
+        simd_batch_portable::Benchmark<8>,
+        8,
+        |b, rng, batch| b.run_optimal_par(time(rng), batch)
+    ),
+    parallel_case!(
+        "simd_batch_16_soa_simulated_portable",
+        simd_batch_portable::Benchmark<16>,
+        16,
+        |b, rng, batch| b.run_optimal_par(time(rng), batch)
+    ),
+    parallel_case!(
+        "vec3_f16",
+        vec3_quantized::Benchmark<F16>,
+        1,
+        |b, rng, batch| b.run_par(time(rng), batch)
+    ),
+    parallel_case!(
+        "vec3_snorm16",
+        vec3_quantized::Benchmark<Snorm16>,
+        1,
+        |b, rng, batch| b.run_par(time(rng), batch)
+    ),
+    parallel_case!(
+        "aosoa_sse4_f16",
+        aosoa_sse4_quantized::Benchmark<F16>,
+        4,
+        |b, rng, batch| b.run_par(time(rng), batch)
+    ),
+    parallel_case!(
+        "aosoa_sse4_snorm16",
+        aosoa_sse4_quantized::Benchmark<Snorm16>,
+        4,
+        |b, rng, batch| b.run_par(time(rng), batch)
+    ),
+];
+
+//A layout's update kernel issuing software prefetches a fixed number of entities ahead.  Benchmarked
+//next to the plain `workload` case it's a variant of, and that case's `_stream` variant writing with
+//non-temporal stores.
+pub struct PrefetchCase {
+    pub layout: &'static str,
+    pub workload: &'static str,
+    //Built from the size and the prefetch distance, in entities
+    pub build: fn(i32, usize) -> Kernel,
+}
+
+macro_rules! prefetch_case {
+    ($layout:expr, $workload:expr, $bench:ty, |$b:ident, $rng:ident, $distance:ident| $run:expr) => {
+        PrefetchCase {
+            layout: $layout,
+            workload: $workload,
+            build: |size, $distance| {
+                let mut $b = <$bench>::new(size);
+                Box::new(move |$rng: &mut ThreadRng| {
+                    $run;
+                })
+            },
+        }
+    };
+}
+
+pub const PREFETCH_CASES: &[PrefetchCase] = &[
+    prefetch_case!(
+        "aosoa_sse4",
+        "update",
+        aosoa_sse4::Benchmark,
+        |b, rng, distance| b.run_prefetch(time(rng), distance)
+    ),
+    prefetch_case!(
+        "simd_batch_4_soa_simulated",
+        "update_nochangedetect",
+        simd_batch_sse4::Benchmark,
+        |b, rng, distance| b.run_optimal_nochange_prefetch(time(rng), distance)
+    ),
+    prefetch_case!(
+        "aosoa_portable_4",
+        "update",
+        aosoa_portable::Benchmark<4>,
+        |b, rng, distance| b.run_prefetch(time(rng), distance)
+    ),
+    prefetch_case!(
+        "aosoa_portable_8",
+        "update",
+        aosoa_portable::Benchmark<8>,
+        |b, rng, distance| b.run_prefetch(time(rng), distance)
+    ),
+    prefetch_case!(
+        "aosoa_portable_16",
+        "update",
+        aosoa_portable::Benchmark<16>,
+        |b, rng, distance| b.run_prefetch(time(rng), distance)
+    ),
+    prefetch_case!(
+        "simd_batch_4_soa_simulated_portable",
+        "update_nochangedetect",
+        simd_batch_portable::Benchmark<4>,
+        |b, rng, distance| b.run_optimal_nochange_prefetch(time(rng), distance)
+    ),
+    prefetch_case!(
+        "simd_batch_8_soa_simulated_portable",
+        "update_nochangedetect",
+        simd_batch_portable::Benchmark<8>,
+        |b, rng, distance| b.run_optimal_nochange_prefetch(time(rng), distance)
+    ),
+    prefetch_case!(
+        "simd_batch_16_soa_simulated_portable",
+        "update_nochangedetect",
+        simd_batch_portable::Benchmark<16>,
+        |b, rng, distance| b.run_optimal_nochange_prefetch(time(rng), distance)
+    ),
+];
+
+//One frame of a layout's change detection benchmark: the update, then with `consume` set a query over
+//the positions it changed.  Returns the virtual entities that query visited.
+pub type ChangeKernel = Box<dyn FnMut(&mut ThreadRng, bool) -> usize>;
+
+//A layout's update writing only the virtual entities that move, with a downstream `Changed<Position>`
+//consumer
+pub struct ChangeCase {
+    pub layout: &'static str,
+    //Built from the size and the fraction of stationary virtual entities.  Also returns how many
+    //virtual entities were left moving.
+    pub build: fn(i32, f64) -> (usize, ChangeKernel),
+}
+
+macro_rules! change_case {
+    ($layout:expr, $change_benchmark:expr) => {
+        ChangeCase {
+            layout: $layout,
+            build: |size, stationary_fraction| {
+                let mut b = $change_benchmark(size, stationary_fraction);
+                let moving = b.moving();
+                let kernel: ChangeKernel =
+                    Box::new(move |rng: &mut ThreadRng, consume: bool| b.run(time(rng), consume));
+                (moving, kernel)
+            },
+        }
+    };
+}
+
+pub const CHANGE_CASES: &[ChangeCase] = &[
+    change_case!(
+        "naive_batched_swizzle",
+        naive_batched_swizzle::change_benchmark
+    ),
+    change_case!("aosoa_sse4", aosoa_sse4::change_benchmark),
+    change_case!(
+        "simd_batch_4_soa_simulated",
+        simd_batch_sse4::ChangeBenchmark::new
+    ),
+    change_case!("aosoa_portable_4", aosoa_portable::change_benchmark::<4>),
+    change_case!("aosoa_portable_8", aosoa_portable::change_benchmark::<8>),
+    change_case!("aosoa_portable_16", aosoa_portable::change_benchmark::<16>),
+    change_case!(
+        "simd_batch_4_soa_simulated_portable",
+        simd_batch_portable::ChangeBenchmark::<4>::new
+    ),
+    change_case!(
+        "simd_batch_8_soa_simulated_portable",
+        simd_batch_portable::ChangeBenchmark::<8>::new
+    ),
+    change_case!(
+        "simd_batch_16_soa_simulated_portable",
+        simd_batch_portable::ChangeBenchmark::<16>::new
+    ),
+    change_case!("vec3_f16", vec3_quantized::change_benchmark::<F16>),
+    change_case!("vec3_snorm16", vec3_quantized::change_benchmark::<Snorm16>),
+    change_case!(
+        "aosoa_sse4_f16",
+        aosoa_sse4_quantized::change_benchmark::<F16>
+    ),
+    change_case!(
+        "aosoa_sse4_snorm16",
+        aosoa_sse4_quantized::change_benchmark::<Snorm16>
+    ),
+];
+
+//A layout's spawn, insert and remove costs, see lifecycle.rs
+pub struct LifecycleCase {
+    pub layout: &'static str,
+    //Built from the size, outside the timing
+    pub prepare: fn(i32, Operation) -> Prepared,
+}
+
+pub const LIFECYCLE_CASES: &[LifecycleCase] = &[
+    LifecycleCase {
+        layout: "naive_batched_swizzle",
+        prepare: naive_batched_swizzle::lifecycle,
+    },
+    LifecycleCase {
+        layout: "aosoa_sse4",
+        prepare: aosoa_sse4::lifecycle,
+    },
+    LifecycleCase {
+        layout: "simd_batch_4_soa_simulated",
+        prepare: simd_batch_sse4::lifecycle,
+    },
+    LifecycleCase {
+        layout: "aosoa_portable_4",
+        prepare: aosoa_portable::lifecycle::<4>,
+    },
+    LifecycleCase {
+        layout: "aosoa_portable_8",
+        prepare: aosoa_portable::lifecycle::<8>,
+    },
+    LifecycleCase {
+        layout: "aosoa_portable_16",
+        prepare: aosoa_portable::lifecycle::<16>,
+    },
+    LifecycleCase {
+        layout: "simd_batch_4_soa_simulated_portable",
+        prepare: simd_batch_portable::lifecycle::<4>,
+    },
+    LifecycleCase {
+        layout: "simd_batch_8_soa_simulated_portable",
+        prepare: simd_batch_portable::lifecycle::<8>,
+    },
+    LifecycleCase {
+        layout: "simd_batch_16_soa_simulated_portable",
+        prepare: simd_batch_portable::lifecycle::<16>,
+    },
+    LifecycleCase {
+        layout: "vec3_f16",
+        prepare: vec3_quantized::lifecycle::<F16>,
+    },
+    LifecycleCase {
+        layout: "vec3_snorm16",
+        prepare: vec3_quantized::lifecycle::<Snorm16>,
+    },
+    LifecycleCase {
+        layout: "aosoa_sse4_f16",
+        prepare: aosoa_sse4_quantized::lifecycle::<F16>,
+    },
+    LifecycleCase {
+        layout: "aosoa_sse4_snorm16",
+        prepare: aosoa_sse4_quantized::lifecycle::<Snorm16>,
+    },
+];
+
+//Builds each layout's world to measure its memory footprint, which doesn't depend on the workload
+pub const FOOTPRINTS: &[(&str, fn(i32) -> Footprint)] = &[
+    ("vec3", footprint::vec3),
+    ("vec3_batch_4_swizzle", footprint::vec3),
+    ("vec3_batch_8_swizzle", footprint::vec3),
+    ("vec3_aligned", footprint::vec3_aligned),
+    ("soa", footprint::soa),
+    ("soa_batch_4", footprint::soa),
+    ("soa_batch_8", footprint::soa),
+    ("naive_batched_swizzle", naive_batched_swizzle::footprint),
+    ("aosoa_sse4", aosoa_sse4::footprint),
+    ("simd_batch_4_soa_simulated", simd_batch_sse4::footprint),
+    ("aosoa_portable_4", aosoa_portable::footprint::<4>),
+    ("aosoa_portable_8", aosoa_portable::footprint::<8>),
+    ("aosoa_portable_16", aosoa_portable::footprint::<16>),
+    (
+        "simd_batch_4_soa_simulated_portable",
+        simd_batch_portable::footprint::<4>,
+    ),
+    (
+        "simd_batch_8_soa_simulated_portable",
+        simd_batch_portable::footprint::<8>,
+    ),
+    (
+        "simd_batch_16_soa_simulated_portable",
+        simd_batch_portable::footprint::<16>,
+    ),
+    ("soa_columns", soa_columns::footprint),
+    ("vec3_f16", vec3_quantized::footprint::<F16>),
+    ("vec3_snorm16", vec3_quantized::footprint::<Snorm16>),
+    ("aosoa_sse4_f16", aosoa_sse4_quantized::footprint::<F16>),
+    (
+        "aosoa_sse4_snorm16",
+        aosoa_sse4_quantized::footprint::<Snorm16>,
+    ),
+];
+
+//Error of each quantized layout against an f32 reference, given a size and a number of frames
+pub const QUANTIZATION_ERRORS: &[(&str, fn(i32, usize) -> ErrorStats)] = &[
+    ("vec3_f16", vec3_quantized::error_stats::<F16>),
+    ("vec3_snorm16", vec3_quantized::error_stats::<Snorm16>),
+    ("aosoa_sse4_f16", aosoa_sse4_quantized::error_stats::<F16>),
+    (
+        "aosoa_sse4_snorm16",
+        aosoa_sse4_quantized::error_stats::<Snorm16>,
+    ),
+];
+
+//Virtual entities in the world built to measure a layout's bytes per element
+const FOOTPRINT_SAMPLE_SIZE: i32 = 4096;
+
+//Component and tick bytes per virtual entity, which is the working set of every workload on the layout
+pub fn bytes_per_element(layout: &str) -> Option<f64> {
+    FOOTPRINTS
+        .iter()
+        .find(|(known, _)| *known == layout)
+        .and_then(|(_, measure)| measure(FOOTPRINT_SAMPLE_SIZE).bytes_per_virtual_entity())
+}
+
+pub fn find(layout: &str, workload: &str) -> Option<&'static Case> {
+    CASES
+        .iter()
+        .find(|case| case.layout == layout && case.workload == workload)
+}
+
+//The AoSoA and simulated SoA update kernels, in glam and in std::simd at every lane count, which the
+//portable SIMD comparison runs side by side
+pub fn portable_simd_cases() -> impl Iterator<Item = &'static Case> {
+    CASES.iter().filter(|case| {
+        let aosoa = case.layout == "aosoa_sse4" || case.layout.starts_with("aosoa_portable_");
+        let soa = case.layout == "simd_batch_4_soa_simulated"
+            || case.layout.ends_with("_soa_simulated_portable");
+        (aosoa && case.workload == "update") || (soa && case.workload == "update_nochangedetect")
+    })
+}
+
+//The cases whose world can be built from a Setup, which the input and world shape sweeps run over
+pub fn setup_cases() -> impl Iterator<Item = &'static Case> {
+    CASES.iter().filter(|case| case.build_with_setup.is_some())
+}
@@ -0,0 +1,40 @@
+use bevy::prelude::*;
+
+use super::inputs::InputDistribution;
+
+//How a layout's world is built for a case: its components are drawn from `inputs`, then the spawned world
+//goes through `transform`, which reshapes it (spreading it over archetypes, adding cold components, ...)
+//without the layout having to know about it
+pub struct Setup<'a> {
+    pub inputs: InputDistribution,
+    pub transform: &'a dyn Fn(World) -> World,
+}
+
+impl<'a> Setup<'a> {
+    pub fn with_inputs(inputs: InputDistribution) -> Self {
+        Self {
+            inputs,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_transform(transform: &'a dyn Fn(World) -> World) -> Self {
+        Self {
+            transform,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for Setup<'_> {
+    fn default() -> Self {
+        Self {
+            inputs: InputDistribution::default(),
+            transform: &unchanged,
+        }
+    }
+}
+
+fn unchanged(world: World) -> World {
+    world
+}
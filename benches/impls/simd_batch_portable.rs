@@ -0,0 +1,564 @@
+use bevy::ecs::component::TableStorage;
+use bevy::prelude::*;
+use criterion::black_box;
+use rand::rngs::ThreadRng;
+
+use std::marker::PhantomData;
+use std::simd::num::SimdFloat;
+use std::simd::{LaneCount, Simd, SupportedLaneCount};
+
+use super::changes::{self, Lanes};
+use super::footprint::{Footprint, F32_PAYLOAD};
+use super::inputs::InputDistribution;
+use super::lifecycle::{self, Operation, Prepared};
+use super::prefetch::{prefetch, stream, stream_fence};
+use super::setup::Setup;
+use super::storage::Storage;
+use super::system::{SystemBenchmark, TimeStep};
+use super::traffic::Traffic;
+use super::transcendental::{oscillate_scalar, oscillate_simd, LaneMath};
+
+struct X;
+struct Y;
+struct Z;
+
+//Same "virtual entity" SoA scheme as simd_batch_sse4, but N-wide and using std::simd instead of glam
+struct Position<Name, const N: usize, S: Storage = TableStorage>(
+    Simd<f32, N>,
+    PhantomData<(Name, S)>,
+)
+where
+    LaneCount<N>: SupportedLaneCount;
+
+impl<Name: Send + Sync + 'static, const N: usize, S: Storage> Component for Position<Name, N, S>
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    type Storage = S;
+}
+
+impl<Name, const N: usize, S: Storage> From<Simd<f32, N>> for Position<Name, N, S>
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    fn from(x: Simd<f32, N>) -> Self {
+        Self(x, PhantomData)
+    }
+}
+
+struct Velocity<Name, const N: usize, S: Storage = TableStorage>(
+    Simd<f32, N>,
+    PhantomData<(Name, S)>,
+)
+where
+    LaneCount<N>: SupportedLaneCount;
+
+impl<Name: Send + Sync + 'static, const N: usize, S: Storage> Component for Velocity<Name, N, S>
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    type Storage = S;
+}
+
+impl<Name, const N: usize, S: Storage> From<Simd<f32, N>> for Velocity<Name, N, S>
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    fn from(x: Simd<f32, N>) -> Self {
+        Self(x, PhantomData)
+    }
+}
+
+#[derive(Bundle)]
+struct MovingBundle<const N: usize, S: Storage>
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    px: Position<X, N, S>,
+    py: Position<Y, N, S>,
+    pz: Position<Z, N, S>,
+    vx: Velocity<X, N, S>,
+    vy: Velocity<Y, N, S>,
+    vz: Velocity<Z, N, S>,
+}
+
+pub struct Benchmark<'w, const N: usize, S: Storage = TableStorage>(
+    World,
+    QueryState<(&'w Velocity<X, N, S>, &'w mut Position<X, N, S>)>,
+    QueryState<(&'w Velocity<Y, N, S>, &'w mut Position<Y, N, S>)>,
+    QueryState<(&'w Velocity<Z, N, S>, &'w mut Position<Z, N, S>)>,
+    QueryState<(
+        &'w Velocity<X, N, S>,
+        &'w mut Position<X, N, S>,
+        &'w Velocity<Y, N, S>,
+        &'w mut Position<Y, N, S>,
+        &'w Velocity<Z, N, S>,
+        &'w mut Position<Z, N, S>,
+    )>,
+)
+where
+    LaneCount<N>: SupportedLaneCount;
+
+//Bytes touched per virtual entity by the update kernel, over all three axes
+pub fn traffic<const N: usize>() -> Traffic
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    let axis = Traffic::update::<Velocity<X, N>, Position<X, N>>(N);
+    Traffic {
+        read: 3.0 * axis.read,
+        written: 3.0 * axis.written,
+    }
+}
+
+//Memory the world holds for this layout
+pub fn footprint<const N: usize>(size: i32) -> Footprint
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    Footprint::of(&Benchmark::<N>::new(size).0, N, F32_PAYLOAD)
+}
+
+//Spawning, inserting into or removing from `size` virtual entities of this layout, see lifecycle.rs
+pub fn lifecycle<const N: usize>(size: i32, operation: Operation) -> Prepared
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    lifecycle::prepare(
+        size as usize / N,
+        operation,
+        moving_bundle::<N, TableStorage>,
+        position_bundle::<N, TableStorage>,
+        velocity_bundle::<N, TableStorage>,
+    )
+}
+
+fn moving_bundle<const N: usize, S: Storage>(
+    inputs: &InputDistribution,
+    rng: &mut ThreadRng,
+) -> MovingBundle<N, S>
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    let (px, py, pz) = position_bundle(inputs, rng);
+    let (vx, vy, vz) = velocity_bundle(inputs, rng);
+
+    MovingBundle {
+        px,
+        py,
+        pz,
+        vx,
+        vy,
+        vz,
+    }
+}
+
+fn position_bundle<const N: usize, S: Storage>(
+    inputs: &InputDistribution,
+    rng: &mut ThreadRng,
+) -> (Position<X, N, S>, Position<Y, N, S>, Position<Z, N, S>)
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    let [pxs, pys, pzs] =
+        [0, 1, 2].map(|_| Simd::from_array([(); N].map(|_| inputs.position(rng))));
+    (pxs.into(), pys.into(), pzs.into())
+}
+
+fn velocity_bundle<const N: usize, S: Storage>(
+    inputs: &InputDistribution,
+    rng: &mut ThreadRng,
+) -> (Velocity<X, N, S>, Velocity<Y, N, S>, Velocity<Z, N, S>)
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    let [vxs, vys, vzs] =
+        [0, 1, 2].map(|_| Simd::from_array([(); N].map(|_| inputs.velocity(rng))));
+    (vxs.into(), vys.into(), vzs.into())
+}
+
+impl<'w, const N: usize> Benchmark<'w, N>
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    pub fn new(size: i32) -> Self {
+        Self::with_storage(size)
+    }
+
+    //The world spawned from `setup.inputs`, then passed through `setup.transform`
+    pub fn with_setup(size: i32, setup: &Setup) -> Self {
+        let mut bench = Self::spawn(size, &setup.inputs);
+        bench.0 = (setup.transform)(std::mem::take(&mut bench.0));
+        bench
+    }
+}
+
+impl<'w, const N: usize, S: Storage> Benchmark<'w, N, S>
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    pub fn with_storage(size: i32) -> Self {
+        Self::spawn(size, &InputDistribution::default())
+    }
+
+    fn spawn(size: i32, inputs: &InputDistribution) -> Self {
+        let size = size / N as i32; //N "virtual entities" per identity
+
+        let mut world = World::default();
+
+        let mut rng = rand::thread_rng();
+
+        world.spawn_batch((0..size).map(|_| moving_bundle::<N, S>(inputs, &mut rng)));
+
+        let query_x = world.query::<(&Velocity<X, N, S>, &mut Position<X, N, S>)>();
+
+        let query_y = world.query::<(&Velocity<Y, N, S>, &mut Position<Y, N, S>)>();
+
+        let query_z = world.query::<(&Velocity<Z, N, S>, &mut Position<Z, N, S>)>();
+
+        let query_full = world.query::<(
+            &Velocity<X, N, S>,
+            &mut Position<X, N, S>,
+            &Velocity<Y, N, S>,
+            &mut Position<Y, N, S>,
+            &Velocity<Z, N, S>,
+            &mut Position<Z, N, S>,
+        )>();
+
+        Self(world, query_x, query_y, query_z, query_full)
+    }
+
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_optimal(&mut self, time: f32) {
+        let time = Simd::splat(time);
+
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                update(time, velocity, &mut position)
+            });
+        self.2
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                update(time, velocity, &mut position)
+            });
+        self.3
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                update(time, velocity, &mut position)
+            });
+    }
+
+    //Same as run_optimal, each axis split over the ComputeTaskPool in tasks of `batch_size` entities
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_optimal_par(&mut self, time: f32, batch_size: usize) {
+        let time = Simd::splat(time);
+
+        self.1
+            .par_for_each_mut(&mut self.0, batch_size, |(velocity, mut position)| {
+                update(time, velocity, &mut position)
+            });
+        self.2
+            .par_for_each_mut(&mut self.0, batch_size, |(velocity, mut position)| {
+                update(time, velocity, &mut position)
+            });
+        self.3
+            .par_for_each_mut(&mut self.0, batch_size, |(velocity, mut position)| {
+                update(time, velocity, &mut position)
+            });
+    }
+
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_optimal_nochange(&mut self, time: f32) {
+        let time = Simd::splat(time);
+
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                position.bypass_change_detection().0 += time * velocity.0;
+            });
+        self.2
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                position.bypass_change_detection().0 += time * velocity.0;
+            });
+        self.3
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                position.bypass_change_detection().0 += time * velocity.0;
+            });
+    }
+
+    //Same as run_optimal_nochange, but prefetches the components `distance` entities ahead
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_optimal_nochange_prefetch(&mut self, time: f32, distance: usize) {
+        let time = Simd::splat(time);
+
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                prefetch(velocity, distance);
+                let position = position.bypass_change_detection();
+                prefetch(&*position, distance);
+                update(time, velocity, position)
+            });
+        self.2
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                prefetch(velocity, distance);
+                let position = position.bypass_change_detection();
+                prefetch(&*position, distance);
+                update(time, velocity, position)
+            });
+        self.3
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                prefetch(velocity, distance);
+                let position = position.bypass_change_detection();
+                prefetch(&*position, distance);
+                update(time, velocity, position)
+            });
+    }
+
+    //Same as run_optimal_nochange, but writes the results with non-temporal stores
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_optimal_nochange_stream(&mut self, time: f32) {
+        let time = Simd::splat(time);
+
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                update_with(time, velocity, position.bypass_change_detection(), stream)
+            });
+        self.2
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                update_with(time, velocity, position.bypass_change_detection(), stream)
+            });
+        self.3
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                update_with(time, velocity, position.bypass_change_detection(), stream)
+            });
+        stream_fence();
+    }
+
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_suboptimal(&mut self, time: f32) {
+        let time = Simd::splat(time);
+
+        self.4.for_each_mut(
+            &mut self.0,
+            |(
+                velocity_x,
+                mut position_x,
+                velocity_y,
+                mut position_y,
+                velocity_z,
+                mut position_z,
+            )| {
+                position_x.0 += time * velocity_x.0;
+                position_y.0 += time * velocity_y.0;
+                position_z.0 += time * velocity_z.0;
+            },
+        );
+    }
+
+    //Transcendental workload.  Needs x, y and z together, so it has to go through the merged query.
+    //One virtual entity (lane) at a time through libm.
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_oscillator_libm(&mut self, time: f32) {
+        self.4.for_each_mut(
+            &mut self.0,
+            |(
+                velocity_x,
+                mut position_x,
+                velocity_y,
+                mut position_y,
+                velocity_z,
+                mut position_z,
+            )| {
+                for i in 0..N {
+                    let p = Vec3::new(position_x.0[i], position_y.0[i], position_z.0[i]);
+                    let v = Vec3::new(velocity_x.0[i], velocity_y.0[i], velocity_z.0[i]);
+                    let n = oscillate_scalar(p, v, time);
+                    position_x.0[i] = n.x;
+                    position_y.0[i] = n.y;
+                    position_z.0[i] = n.z;
+                }
+            },
+        );
+    }
+
+    //Transcendental workload, 4 of the N lanes at a time
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_oscillator<M: LaneMath>(&mut self, time: f32) {
+        self.4.for_each_mut(
+            &mut self.0,
+            |(
+                velocity_x,
+                mut position_x,
+                velocity_y,
+                mut position_y,
+                velocity_z,
+                mut position_z,
+            )| {
+                let [nxs, nys, nzs] = oscillate_simd::<M, N>(
+                    [position_x.0, position_y.0, position_z.0],
+                    [velocity_x.0, velocity_y.0, velocity_z.0],
+                    time,
+                );
+                position_x.0 = nxs;
+                position_y.0 = nys;
+                position_z.0 = nzs;
+            },
+        );
+    }
+}
+
+//The update kernel of run_optimal, run_optimal_par, run_optimal_nochange_prefetch and the system, for
+//one axis
+#[inline(always)]
+fn update<Name, const N: usize, S: Storage>(
+    time: Simd<f32, N>,
+    velocity: &Velocity<Name, N, S>,
+    position: &mut Position<Name, N, S>,
+) where
+    LaneCount<N>: SupportedLaneCount,
+{
+    update_with(time, velocity, position, |dst, value| *dst = value)
+}
+
+//The update kernel for one axis, writing the result back through `store`
+#[inline(always)]
+fn update_with<Name, const N: usize, S: Storage>(
+    time: Simd<f32, N>,
+    velocity: &Velocity<Name, N, S>,
+    position: &mut Position<Name, N, S>,
+    store: impl Fn(&mut Simd<f32, N>, Simd<f32, N>),
+) where
+    LaneCount<N>: SupportedLaneCount,
+{
+    let result = position.0 + time * velocity.0;
+    store(&mut position.0, result);
+}
+
+//run_optimal as a regular system
+fn update_system<const N: usize>(
+    time: Res<TimeStep>,
+    mut query_x: Query<(&Velocity<X, N>, &mut Position<X, N>)>,
+    mut query_y: Query<(&Velocity<Y, N>, &mut Position<Y, N>)>,
+    mut query_z: Query<(&Velocity<Z, N>, &mut Position<Z, N>)>,
+) where
+    LaneCount<N>: SupportedLaneCount,
+{
+    let time = Simd::splat(time.0);
+    query_x.for_each_mut(|(velocity, mut position)| update(time, velocity, &mut position));
+    query_y.for_each_mut(|(velocity, mut position)| update(time, velocity, &mut position));
+    query_z.for_each_mut(|(velocity, mut position)| update(time, velocity, &mut position));
+}
+
+pub fn system_benchmark<const N: usize>(size: i32) -> SystemBenchmark
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    SystemBenchmark::new(Benchmark::<N>::new(size).0, update_system::<N>)
+}
+
+impl<Name: Send + Sync + 'static, const N: usize> Lanes<Position<Name, N>> for Velocity<Name, N>
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    const LANES: usize = N;
+
+    #[inline(always)]
+    fn update(time: f32, velocity: &Self, position: &mut Position<Name, N>) {
+        update(Simd::splat(time), velocity, position)
+    }
+
+    #[inline(always)]
+    fn moving(&self) -> bool {
+        self.0 != Simd::splat(0.0)
+    }
+
+    fn stop(&mut self, lane: usize) {
+        self.0[lane] = 0.0;
+    }
+
+    #[inline(always)]
+    fn read(position: &Position<Name, N>) -> f32 {
+        position.0.reduce_sum()
+    }
+}
+
+//Change detection benchmark: stationary virtual entities and a consumer of the changed positions.
+//Each axis is its own component, so the update runs once per axis and the consumer takes an entity if
+//any of its three changed.
+pub struct ChangeBenchmark<'w, const N: usize>(
+    Benchmark<'w, N>,
+    QueryState<
+        (&'w Position<X, N>, &'w Position<Y, N>, &'w Position<Z, N>),
+        Or<(
+            Changed<Position<X, N>>,
+            Changed<Position<Y, N>>,
+            Changed<Position<Z, N>>,
+        )>,
+    >,
+    usize,
+)
+where
+    LaneCount<N>: SupportedLaneCount;
+
+impl<'w, const N: usize> ChangeBenchmark<'w, N>
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    pub fn new(size: i32, stationary_fraction: f64) -> Self {
+        let mut bench = Benchmark::<N>::new(size);
+        let mut rng = rand::thread_rng();
+        let mut moving = 0;
+
+        let mut velocities = bench.0.query::<(
+            &mut Velocity<X, N>,
+            &mut Velocity<Y, N>,
+            &mut Velocity<Z, N>,
+        )>();
+        velocities.for_each_mut(&mut bench.0, |(mut x, mut y, mut z)| {
+            let lanes = changes::stationary_lanes(&mut rng, stationary_fraction, N);
+            for (lane, stationary) in lanes.into_iter().enumerate() {
+                if stationary {
+                    x.stop(lane);
+                    y.stop(lane);
+                    z.stop(lane);
+                } else {
+                    moving += 1;
+                }
+            }
+        });
+
+        bench.0.clear_trackers();
+
+        let consumer = bench
+            .0
+            .query_filtered::<(&Position<X, N>, &Position<Y, N>, &Position<Z, N>), Or<(
+                Changed<Position<X, N>>,
+                Changed<Position<Y, N>>,
+                Changed<Position<Z, N>>,
+            )>>();
+        Self(bench, consumer, moving)
+    }
+
+    pub fn moving(&self) -> usize {
+        self.2
+    }
+
+    //run_optimal, skipping axes with no velocity.  Returns the virtual entities the consumer visited.
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run(&mut self, time: f32, consume: bool) -> usize {
+        let bench = &mut self.0;
+        changes::update_moving(&mut bench.1, &mut bench.0, time);
+        changes::update_moving(&mut bench.2, &mut bench.0, time);
+        changes::update_moving(&mut bench.3, &mut bench.0, time);
+
+        let mut visited = 0;
+        if consume {
+            let mut sum = Simd::splat(0.0);
+            self.1.for_each(&bench.0, |(x, y, z)| {
+                sum += x.0 + y.0 + z.0;
+                visited += N;
+            });
+            black_box(sum);
+        }
+
+        bench.0.clear_trackers();
+        visited
+    }
+}
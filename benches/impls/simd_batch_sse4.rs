@@ -1,48 +1,69 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rand::rngs::ThreadRng;
 use rand::Rng;
 
 use std::marker::PhantomData;
 
+use bevy::ecs::component::TableStorage;
+use bevy::ecs::system::SystemState;
 use bevy::prelude::*;
 
+use super::changes::{self, Lanes};
+use super::footprint::{Footprint, F32_PAYLOAD};
+use super::gather::{self, LaneParents, ParentOrder};
+use super::inputs::InputDistribution;
+use super::lifecycle::{self, Operation, Prepared};
+use super::prefetch::{prefetch, stream, stream_fence};
+use super::setup::Setup;
+use super::storage::Storage;
+use super::system::{SystemBenchmark, TimeStep};
+use super::traffic::Traffic;
+use super::transcendental::{oscillate_lanes, oscillate_scalar, LaneMath};
+
 struct X;
 struct Y;
 struct Z;
 
 //Store a Vec4 for the "virtual entity" scheme
-#[derive(Component)]
-struct Position<Name>(Vec4, PhantomData<Name>);
+struct Position<Name, S: Storage = TableStorage>(Vec4, PhantomData<(Name, S)>);
+
+impl<Name: Send + Sync + 'static, S: Storage> Component for Position<Name, S> {
+    type Storage = S;
+}
 
-impl<Name> From<Vec4> for Position<Name> {
+impl<Name, S: Storage> From<Vec4> for Position<Name, S> {
     fn from(x: Vec4) -> Self {
         Self(x, PhantomData)
     }
 }
 
 #[derive(Bundle)]
-struct PositionBundle {
-    x: Position<X>,
-    y: Position<Y>,
-    z: Position<Z>,
+struct PositionBundle<S: Storage> {
+    x: Position<X, S>,
+    y: Position<Y, S>,
+    z: Position<Z, S>,
 }
 
-#[derive(Component)]
-struct Velocity<Name>(Vec4, PhantomData<Name>);
+struct Velocity<Name, S: Storage = TableStorage>(Vec4, PhantomData<(Name, S)>);
+
+impl<Name: Send + Sync + 'static, S: Storage> Component for Velocity<Name, S> {
+    type Storage = S;
+}
 
-impl<Name> From<Vec4> for Velocity<Name> {
+impl<Name, S: Storage> From<Vec4> for Velocity<Name, S> {
     fn from(x: Vec4) -> Self {
         Self(x, PhantomData)
     }
 }
 
 #[derive(Bundle)]
-struct VelocityBundle {
-    x: Velocity<X>,
-    y: Velocity<Y>,
-    z: Velocity<Z>,
+struct VelocityBundle<S: Storage> {
+    x: Velocity<X, S>,
+    y: Velocity<Y, S>,
+    z: Velocity<Z, S>,
 }
 
-impl VelocityBundle {
+impl<S: Storage> VelocityBundle<S> {
     fn new(xs: Vec4, ys: Vec4, zs: Vec4) -> Self {
         Self {
             x: xs.into(),
@@ -52,7 +73,7 @@ impl VelocityBundle {
     }
 }
 
-impl PositionBundle {
+impl<S: Storage> PositionBundle<S> {
     fn new(xs: Vec4, ys: Vec4, zs: Vec4) -> Self {
         Self {
             x: xs.into(),
@@ -63,98 +84,114 @@ impl PositionBundle {
 }
 
 #[derive(Bundle)]
-struct MovingBundle {
-    position: PositionBundle,
-    velocity: VelocityBundle,
-}
-
-impl MovingBundle {
-    fn new(pxs: Vec4, pys: Vec4, pzs: Vec4, vxs: Vec4, vys: Vec4, vzs: Vec4) -> Self {
-        Self {
-            position: PositionBundle::new(pxs, pys, pzs),
-            velocity: VelocityBundle::new(vxs, vys, vzs),
-        }
-    }
+struct MovingBundle<S: Storage> {
+    position: PositionBundle<S>,
+    velocity: VelocityBundle<S>,
 }
 
-pub struct Benchmark<'w>(
+pub struct Benchmark<'w, S: Storage = TableStorage>(
     World,
-    QueryState<(&'w Velocity<X>, &'w mut Position<X>)>,
-    QueryState<(&'w Velocity<Y>, &'w mut Position<Y>)>,
-    QueryState<(&'w Velocity<Z>, &'w mut Position<Z>)>,
+    QueryState<(&'w Velocity<X, S>, &'w mut Position<X, S>)>,
+    QueryState<(&'w Velocity<Y, S>, &'w mut Position<Y, S>)>,
+    QueryState<(&'w Velocity<Z, S>, &'w mut Position<Z, S>)>,
     QueryState<(
-        &'w Velocity<X>,
-        &'w mut Position<X>,
-        &'w Velocity<Y>,
-        &'w mut Position<Y>,
-        &'w Velocity<Z>,
-        &'w mut Position<Z>,
+        &'w Velocity<X, S>,
+        &'w mut Position<X, S>,
+        &'w Velocity<Y, S>,
+        &'w mut Position<Y, S>,
+        &'w Velocity<Z, S>,
+        &'w mut Position<Z, S>,
     )>,
 );
 
+//Bytes touched per virtual entity by the update and oscillator kernels, over all three axes
+pub fn traffic() -> Traffic {
+    let axis = Traffic::update::<Velocity<X>, Position<X>>(4);
+    Traffic {
+        read: 3.0 * axis.read,
+        written: 3.0 * axis.written,
+    }
+}
+
+//Memory the world holds for this layout
+pub fn footprint(size: i32) -> Footprint {
+    Footprint::of(&Benchmark::new(size).0, 4, F32_PAYLOAD)
+}
+
+//Spawning, inserting into or removing from `size` virtual entities of this layout, see lifecycle.rs
+pub fn lifecycle(size: i32, operation: Operation) -> Prepared {
+    lifecycle::prepare(
+        size as usize / 4,
+        operation,
+        moving_bundle::<TableStorage>,
+        position_bundle::<TableStorage>,
+        velocity_bundle::<TableStorage>,
+    )
+}
+
+fn moving_bundle<S: Storage>(inputs: &InputDistribution, rng: &mut ThreadRng) -> MovingBundle<S> {
+    MovingBundle {
+        position: position_bundle(inputs, rng),
+        velocity: velocity_bundle(inputs, rng),
+    }
+}
+
+fn position_bundle<S: Storage>(
+    inputs: &InputDistribution,
+    rng: &mut ThreadRng,
+) -> PositionBundle<S> {
+    let [pxs, pys, pzs] = [0, 1, 2].map(|_| inputs.position4(rng));
+    PositionBundle::new(pxs, pys, pzs)
+}
+
+fn velocity_bundle<S: Storage>(
+    inputs: &InputDistribution,
+    rng: &mut ThreadRng,
+) -> VelocityBundle<S> {
+    let [vxs, vys, vzs] = [0, 1, 2].map(|_| inputs.velocity4(rng));
+    VelocityBundle::new(vxs, vys, vzs)
+}
+
 impl<'w> Benchmark<'w> {
     pub fn new(size: i32) -> Self {
+        Self::with_storage(size)
+    }
+
+    //The world spawned from `setup.inputs`, then passed through `setup.transform`
+    pub fn with_setup(size: i32, setup: &Setup) -> Self {
+        let mut bench = Self::spawn(size, &setup.inputs);
+        bench.0 = (setup.transform)(std::mem::take(&mut bench.0));
+        bench
+    }
+}
+
+impl<'w, S: Storage> Benchmark<'w, S> {
+    pub fn with_storage(size: i32) -> Self {
+        Self::spawn(size, &InputDistribution::default())
+    }
+
+    fn spawn(size: i32, inputs: &InputDistribution) -> Self {
         let size = size / 4; //4 "virtual entities" per identity
 
         let mut world = World::default();
 
         let mut rng = rand::thread_rng();
 
-        world.spawn_batch((0..size).map(|_| {
-            let pxs = Vec4::new(
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-            );
-            let pys = Vec4::new(
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-            );
-            let pzs = Vec4::new(
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-            );
-
-            let vxs = Vec4::new(
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-            );
-            let vys = Vec4::new(
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-            );
-            let vzs = Vec4::new(
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-                rng.gen_range(-16.0..=16.0),
-            );
-
-            MovingBundle::new(pxs, pys, pzs, vxs, vys, vzs)
-        }));
-
-        let query_x = world.query::<(&Velocity<X>, &mut Position<X>)>();
-
-        let query_y = world.query::<(&Velocity<Y>, &mut Position<Y>)>();
-
-        let query_z = world.query::<(&Velocity<Z>, &mut Position<Z>)>();
+        world.spawn_batch((0..size).map(|_| moving_bundle::<S>(inputs, &mut rng)));
+
+        let query_x = world.query::<(&Velocity<X, S>, &mut Position<X, S>)>();
+
+        let query_y = world.query::<(&Velocity<Y, S>, &mut Position<Y, S>)>();
+
+        let query_z = world.query::<(&Velocity<Z, S>, &mut Position<Z, S>)>();
 
         let query_full = world.query::<(
-            &Velocity<X>,
-            &mut Position<X>,
-            &Velocity<Y>,
-            &mut Position<Y>,
-            &Velocity<Z>,
-            &mut Position<Z>,
+            &Velocity<X, S>,
+            &mut Position<X, S>,
+            &Velocity<Y, S>,
+            &mut Position<Y, S>,
+            &Velocity<Z, S>,
+            &mut Position<Z, S>,
         )>();
 
         Self(world, query_x, query_y, query_z, query_full)
@@ -162,6 +199,7 @@ impl<'w> Benchmark<'w> {
 
     //TODO: show swizzling approach... also show how an incorrect access pattern will make things worse.
 
+    #[cfg_attr(feature = "audit", inline(never))]
     pub fn run_optimal(&mut self, time: f32) {
         //Ensure sensible access patterns: if we merge the queries into one big query, then we'll incur more
         //cache misses as we'll be accessing x, y, and z in order, and they likely won't be near each other in memory.
@@ -169,18 +207,36 @@ impl<'w> Benchmark<'w> {
 
         self.1
             .for_each_mut(&mut self.0, |(velocity, mut position)| {
-                position.0 += time * velocity.0;
+                update(time, velocity, &mut position)
             });
         self.2
             .for_each_mut(&mut self.0, |(velocity, mut position)| {
-                position.0 += time * velocity.0;
+                update(time, velocity, &mut position)
             });
         self.3
             .for_each_mut(&mut self.0, |(velocity, mut position)| {
-                position.0 += time * velocity.0;
+                update(time, velocity, &mut position)
+            });
+    }
+
+    //Same as run_optimal, each axis split over the ComputeTaskPool in tasks of `batch_size` entities
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_optimal_par(&mut self, time: f32, batch_size: usize) {
+        self.1
+            .par_for_each_mut(&mut self.0, batch_size, |(velocity, mut position)| {
+                update(time, velocity, &mut position)
+            });
+        self.2
+            .par_for_each_mut(&mut self.0, batch_size, |(velocity, mut position)| {
+                update(time, velocity, &mut position)
+            });
+        self.3
+            .par_for_each_mut(&mut self.0, batch_size, |(velocity, mut position)| {
+                update(time, velocity, &mut position)
             });
     }
 
+    #[cfg_attr(feature = "audit", inline(never))]
     pub fn run_optimal_nochange(&mut self, time: f32) {
         //Ensure sensible access patterns: if we merge the queries into one big query, then we'll incur more
         //cache misses as we'll be accessing x, y, and z in order, and they likely won't be near each other in memory.
@@ -200,6 +256,51 @@ impl<'w> Benchmark<'w> {
             });
     }
 
+    //Same as run_optimal_nochange, but prefetches the components `distance` entities ahead
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_optimal_nochange_prefetch(&mut self, time: f32, distance: usize) {
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                prefetch(velocity, distance);
+                let position = position.bypass_change_detection();
+                prefetch(&*position, distance);
+                update(time, velocity, position)
+            });
+        self.2
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                prefetch(velocity, distance);
+                let position = position.bypass_change_detection();
+                prefetch(&*position, distance);
+                update(time, velocity, position)
+            });
+        self.3
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                prefetch(velocity, distance);
+                let position = position.bypass_change_detection();
+                prefetch(&*position, distance);
+                update(time, velocity, position)
+            });
+    }
+
+    //Same as run_optimal_nochange, but writes the results with non-temporal stores
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_optimal_nochange_stream(&mut self, time: f32) {
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                update_with(time, velocity, position.bypass_change_detection(), stream)
+            });
+        self.2
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                update_with(time, velocity, position.bypass_change_detection(), stream)
+            });
+        self.3
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                update_with(time, velocity, position.bypass_change_detection(), stream)
+            });
+        stream_fence();
+    }
+
+    #[cfg_attr(feature = "audit", inline(never))]
     pub fn run_suboptimal(&mut self, time: f32) {
         self.4.for_each_mut(
             &mut self.0,
@@ -217,4 +318,252 @@ impl<'w> Benchmark<'w> {
             },
         );
     }
+
+    //Transcendental workload.  Needs x, y and z together, so it has to go through the merged query.
+    //One virtual entity (lane) at a time through libm.
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_oscillator_libm(&mut self, time: f32) {
+        self.4.for_each_mut(
+            &mut self.0,
+            |(
+                velocity_x,
+                mut position_x,
+                velocity_y,
+                mut position_y,
+                velocity_z,
+                mut position_z,
+            )| {
+                for i in 0..4 {
+                    let p = Vec3::new(position_x.0[i], position_y.0[i], position_z.0[i]);
+                    let v = Vec3::new(velocity_x.0[i], velocity_y.0[i], velocity_z.0[i]);
+                    let n = oscillate_scalar(p, v, time);
+                    position_x.0[i] = n.x;
+                    position_y.0[i] = n.y;
+                    position_z.0[i] = n.z;
+                }
+            },
+        );
+    }
+
+    //Transcendental workload, all 4 lanes at once
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_oscillator<M: LaneMath>(&mut self, time: f32) {
+        self.4.for_each_mut(
+            &mut self.0,
+            |(
+                velocity_x,
+                mut position_x,
+                velocity_y,
+                mut position_y,
+                velocity_z,
+                mut position_z,
+            )| {
+                let [nxs, nys, nzs] = oscillate_lanes::<M>(
+                    [position_x.0, position_y.0, position_z.0],
+                    [velocity_x.0, velocity_y.0, velocity_z.0],
+                    time,
+                );
+                position_x.0 = nxs;
+                position_y.0 = nys;
+                position_z.0 = nzs;
+            },
+        );
+    }
+}
+
+//The update kernel of run_optimal, run_optimal_par, run_optimal_nochange_prefetch and the system, for
+//one axis
+#[inline(always)]
+fn update<Name, S: Storage>(
+    time: f32,
+    velocity: &Velocity<Name, S>,
+    position: &mut Position<Name, S>,
+) {
+    update_with(time, velocity, position, |dst, value| *dst = value)
+}
+
+//The update kernel for one axis, writing the result back through `store`
+#[inline(always)]
+fn update_with<Name, S: Storage>(
+    time: f32,
+    velocity: &Velocity<Name, S>,
+    position: &mut Position<Name, S>,
+    store: impl Fn(&mut Vec4, Vec4),
+) {
+    let result = position.0 + time * velocity.0;
+    store(&mut position.0, result);
+}
+
+//run_optimal as a regular system: one query per axis, walked one after the other
+fn update_system(
+    time: Res<TimeStep>,
+    mut query_x: Query<(&Velocity<X>, &mut Position<X>)>,
+    mut query_y: Query<(&Velocity<Y>, &mut Position<Y>)>,
+    mut query_z: Query<(&Velocity<Z>, &mut Position<Z>)>,
+) {
+    let time = time.0;
+    query_x.for_each_mut(|(velocity, mut position)| update(time, velocity, &mut position));
+    query_y.for_each_mut(|(velocity, mut position)| update(time, velocity, &mut position));
+    query_z.for_each_mut(|(velocity, mut position)| update(time, velocity, &mut position));
+}
+
+pub fn system_benchmark(size: i32) -> SystemBenchmark {
+    SystemBenchmark::new(Benchmark::new(size).0, update_system)
+}
+
+impl<Name: Send + Sync + 'static> Lanes<Position<Name>> for Velocity<Name> {
+    const LANES: usize = 4;
+
+    #[inline(always)]
+    fn update(time: f32, velocity: &Self, position: &mut Position<Name>) {
+        update(time, velocity, position)
+    }
+
+    #[inline(always)]
+    fn moving(&self) -> bool {
+        self.0 != Vec4::ZERO
+    }
+
+    fn stop(&mut self, lane: usize) {
+        self.0[lane] = 0.0;
+    }
+
+    #[inline(always)]
+    fn read(position: &Position<Name>) -> f32 {
+        position.0.dot(Vec4::ONE)
+    }
+}
+
+//Change detection benchmark: stationary virtual entities and a consumer of the changed positions.
+//Each axis is its own component, so the update runs once per axis and the consumer takes an entity if
+//any of its three changed.
+pub struct ChangeBenchmark<'w>(
+    Benchmark<'w>,
+    QueryState<
+        (&'w Position<X>, &'w Position<Y>, &'w Position<Z>),
+        Or<(
+            Changed<Position<X>>,
+            Changed<Position<Y>>,
+            Changed<Position<Z>>,
+        )>,
+    >,
+    usize,
+);
+
+impl<'w> ChangeBenchmark<'w> {
+    pub fn new(size: i32, stationary_fraction: f64) -> Self {
+        let mut bench = Benchmark::new(size);
+        let mut rng = rand::thread_rng();
+        let mut moving = 0;
+
+        let mut velocities = bench
+            .0
+            .query::<(&mut Velocity<X>, &mut Velocity<Y>, &mut Velocity<Z>)>();
+        velocities.for_each_mut(&mut bench.0, |(mut x, mut y, mut z)| {
+            let lanes = changes::stationary_lanes(&mut rng, stationary_fraction, 4);
+            for (lane, stationary) in lanes.into_iter().enumerate() {
+                if stationary {
+                    x.stop(lane);
+                    y.stop(lane);
+                    z.stop(lane);
+                } else {
+                    moving += 1;
+                }
+            }
+        });
+
+        bench.0.clear_trackers();
+
+        let consumer = bench
+            .0
+            .query_filtered::<(&Position<X>, &Position<Y>, &Position<Z>), Or<(
+                Changed<Position<X>>,
+                Changed<Position<Y>>,
+                Changed<Position<Z>>,
+            )>>();
+        Self(bench, consumer, moving)
+    }
+
+    pub fn moving(&self) -> usize {
+        self.2
+    }
+
+    //run_optimal, skipping axes with no velocity.  Returns the virtual entities the consumer visited.
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run(&mut self, time: f32, consume: bool) -> usize {
+        let bench = &mut self.0;
+
+        changes::update_moving(&mut bench.1, &mut bench.0, time);
+        changes::update_moving(&mut bench.2, &mut bench.0, time);
+        changes::update_moving(&mut bench.3, &mut bench.0, time);
+
+        let mut visited = 0;
+        if consume {
+            let mut sum = Vec4::ZERO;
+            self.1.for_each(&bench.0, |(x, y, z)| {
+                sum += x.0 + y.0 + z.0;
+                visited += 4;
+            });
+            black_box(sum);
+        }
+
+        bench.0.clear_trackers();
+        visited
+    }
+}
+
+type GatherParams = (
+    Query<
+        'static,
+        'static,
+        (
+            &'static LaneParents<4>,
+            &'static mut Position<X>,
+            &'static mut Position<Y>,
+            &'static mut Position<Z>,
+        ),
+    >,
+    Query<
+        'static,
+        'static,
+        (
+            &'static Velocity<X>,
+            &'static Velocity<Y>,
+            &'static Velocity<Z>,
+        ),
+    >,
+);
+
+//Gather workload: every virtual entity inherits the velocity of a parent looked up with Query::get.
+//One lookup per lane fetches its parent's block on all three axes, which are then shuffled in one
+//lane at a time.
+pub struct GatherBenchmark(World, SystemState<GatherParams>);
+
+impl GatherBenchmark {
+    pub fn new(size: i32, order: ParentOrder) -> Self {
+        let mut world = Benchmark::new(size).0;
+        gather::assign_lane_parents::<Position<X>, 4>(&mut world, order);
+
+        let state = SystemState::new(&mut world);
+        Self(world, state)
+    }
+
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run(&mut self, time: f32) {
+        let (mut children, velocities) = self.1.get_mut(&mut self.0);
+
+        children.for_each_mut(|(parents, mut x, mut y, mut z)| {
+            let (mut vxs, mut vys, mut vzs) = (Vec4::ZERO, Vec4::ZERO, Vec4::ZERO);
+            for (lane, &(parent, parent_lane)) in parents.0.iter().enumerate() {
+                let (vx, vy, vz) = velocities.get(parent).unwrap();
+                vxs[lane] = vx.0[parent_lane];
+                vys[lane] = vy.0[parent_lane];
+                vzs[lane] = vz.0[parent_lane];
+            }
+
+            x.0 += time * vxs;
+            y.0 += time * vys;
+            z.0 += time * vzs;
+        });
+    }
 }
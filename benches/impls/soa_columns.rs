@@ -0,0 +1,114 @@
+use bevy::prelude::*;
+
+use std::marker::PhantomData;
+
+use super::columns::ColumnQuery;
+use super::footprint::{Footprint, F32_PAYLOAD};
+use super::inputs::InputDistribution;
+use super::traffic::Traffic;
+
+//True SoA: one f32 per axis component and one virtual entity per entity, with no packing into lanes.
+//The kernels get whole table columns through ColumnQuery on each axis' QueryState, so the compiler
+//vectorizes across entities.
+
+struct X;
+struct Y;
+struct Z;
+
+#[derive(Component)]
+struct Position<Name>(f32, PhantomData<Name>);
+
+#[derive(Component)]
+struct Velocity<Name>(f32, PhantomData<Name>);
+
+pub struct Benchmark<'w>(
+    World,
+    QueryState<(&'w Velocity<X>, &'w mut Position<X>)>,
+    QueryState<(&'w Velocity<Y>, &'w mut Position<Y>)>,
+    QueryState<(&'w Velocity<Z>, &'w mut Position<Z>)>,
+);
+
+//Bytes touched per virtual entity by the update kernels
+pub fn traffic() -> Traffic {
+    let axis = Traffic::update::<Velocity<X>, Position<X>>(1);
+    Traffic {
+        read: 3.0 * axis.read,
+        written: 3.0 * axis.written,
+    }
+}
+
+//Memory the world holds for this layout
+pub fn footprint(size: i32) -> Footprint {
+    Footprint::of(&Benchmark::new(size).0, 1, F32_PAYLOAD)
+}
+
+#[inline(always)]
+fn update<Name>(velocities: &[Velocity<Name>], positions: &mut [Position<Name>], time: f32) {
+    for (velocity, position) in velocities.iter().zip(positions) {
+        position.0 += time * velocity.0;
+    }
+}
+
+impl<'w> Benchmark<'w> {
+    pub fn new(size: i32) -> Self {
+        let inputs = InputDistribution::default();
+        let mut rng = rand::thread_rng();
+
+        let mut world = World::new();
+
+        world.spawn_batch((0..size).map(|_| {
+            (
+                Position::<X>(inputs.position(&mut rng), PhantomData),
+                Position::<Y>(inputs.position(&mut rng), PhantomData),
+                Position::<Z>(inputs.position(&mut rng), PhantomData),
+                Velocity::<X>(inputs.velocity(&mut rng), PhantomData),
+                Velocity::<Y>(inputs.velocity(&mut rng), PhantomData),
+                Velocity::<Z>(inputs.velocity(&mut rng), PhantomData),
+            )
+        }));
+
+        let query_x = world.query::<(&Velocity<X>, &mut Position<X>)>();
+        let query_y = world.query::<(&Velocity<Y>, &mut Position<Y>)>();
+        let query_z = world.query::<(&Velocity<Z>, &mut Position<Z>)>();
+
+        Self(world, query_x, query_y, query_z)
+    }
+
+    //One axis at a time over whole columns, marking every position changed
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run(&mut self, time: f32) {
+        self.1
+            .for_each_column_mut(&mut self.0, |v, p| update(v, p, time));
+        self.2
+            .for_each_column_mut(&mut self.0, |v, p| update(v, p, time));
+        self.3
+            .for_each_column_mut(&mut self.0, |v, p| update(v, p, time));
+    }
+
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_nochange(&mut self, time: f32) {
+        self.1
+            .for_each_column_mut_nochange(&mut self.0, |v, p| update(v, p, time));
+        self.2
+            .for_each_column_mut_nochange(&mut self.0, |v, p| update(v, p, time));
+        self.3
+            .for_each_column_mut_nochange(&mut self.0, |v, p| update(v, p, time));
+    }
+
+    //The same layout through regular queries, one entity and so one f32 at a time
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_query(&mut self, time: f32) {
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                position.0 += time * velocity.0;
+            });
+        self.2
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                position.0 += time * velocity.0;
+            });
+        self.3
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                position.0 += time * velocity.0;
+            });
+    }
+}
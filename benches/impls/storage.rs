@@ -0,0 +1,9 @@
+use bevy::ecs::component::{ComponentStorage, SparseStorage, TableStorage};
+
+//Storage a layout's components are compiled with.  Layouts take it as a parameter defaulting to
+//tables, so each of them can also be benchmarked with its components in sparse sets.
+pub trait Storage: ComponentStorage + Send + Sync + 'static {}
+
+impl Storage for TableStorage {}
+
+impl Storage for SparseStorage {}
@@ -0,0 +1,29 @@
+use bevy::ecs::schedule::IntoSystemDescriptor;
+use bevy::prelude::*;
+
+//Time step handed to the systems each frame, read the way production systems read `Res<Time>`
+pub struct TimeStep(pub f32);
+
+#[derive(StageLabel, Debug, Clone, PartialEq, Eq, Hash)]
+struct Update;
+
+//A layout's kernel as a regular system in a one stage Schedule, so it pays for system params,
+//access checks and the parallel executor the way production code does.  Compare against the same
+//kernel run straight through `QueryState` to get the scheduler's overhead.
+pub struct SystemBenchmark(World, Schedule);
+
+impl SystemBenchmark {
+    pub fn new<Params>(mut world: World, system: impl IntoSystemDescriptor<Params>) -> Self {
+        world.insert_resource(TimeStep(0.0));
+
+        let mut schedule = Schedule::default();
+        schedule.add_stage(Update, SystemStage::parallel().with_system(system));
+
+        Self(world, schedule)
+    }
+
+    pub fn run(&mut self, time: f32) {
+        self.0.resource_mut::<TimeStep>().0 = time;
+        self.1.run(&mut self.0);
+    }
+}
@@ -0,0 +1,37 @@
+use std::mem::size_of;
+
+use super::footprint::TICK_BYTES;
+
+//Bytes a kernel moves per virtual entity, as declared by the layout rather than measured.  With the
+//element count this turns a time into an achieved bandwidth.
+#[derive(Debug, Copy, Clone)]
+pub struct Traffic {
+    pub read: f64,
+    pub written: f64,
+}
+
+impl Traffic {
+    //p += t * v: velocity and position are read, position is written back.  `lanes` is the number
+    //of virtual entities packed into each component.
+    pub fn update<Velocity, Position>(lanes: usize) -> Self {
+        let lanes = lanes as f64;
+        Self {
+            read: (size_of::<Velocity>() + size_of::<Position>()) as f64 / lanes,
+            written: size_of::<Position>() as f64 / lanes,
+        }
+    }
+
+    //Writing through `Mut` also stores the change tick of each mutated component.  Only the `changed`
+    //half of its ComponentTicks is written, but `added` sits interleaved next to it, so the whole pair
+    //moves through the cache either way.
+    pub fn with_change_detection(self, mutated_components: usize, lanes: usize) -> Self {
+        Self {
+            read: self.read,
+            written: self.written + (mutated_components * TICK_BYTES) as f64 / lanes as f64,
+        }
+    }
+
+    pub fn bytes(&self) -> f64 {
+        self.read + self.written
+    }
+}
@@ -0,0 +1,260 @@
+use bevy::prelude::*;
+
+use std::simd::cmp::SimdPartialEq;
+use std::simd::num::{SimdFloat, SimdInt};
+use std::simd::{f32x4, i32x4, LaneCount, Simd, StdFloat, SupportedLaneCount};
+
+//Damped oscillator workload.  Per virtual entity:
+//  speed = |v|, phase = speed * t, damping = exp(-DAMPING * phase)
+//  p += t * damping * (v.x * cos(phase), v.y * sin(phase), v.z)
+//so each entity needs one sqrt, exp, sin and cos on top of the usual adds and muls.
+pub const DAMPING: f32 = 0.1;
+
+//sqrt, exp, sin and cos counted as one FLOP each
+pub const OSCILLATOR_FLOPS: f64 = 20.0;
+
+//Scalar form: one virtual entity at a time, straight through std's libm calls
+#[inline(always)]
+pub fn oscillate_scalar(p: Vec3, v: Vec3, t: f32) -> Vec3 {
+    let speed = (v.x * v.x + v.y * v.y + v.z * v.z).sqrt();
+    let phase = speed * t;
+    let damping = (-DAMPING * phase).exp();
+    let (sin, cos) = phase.sin_cos();
+
+    let scale = t * damping;
+    Vec3::new(
+        p.x + scale * cos * v.x,
+        p.y + scale * sin * v.y,
+        p.z + scale * v.z,
+    )
+}
+
+//4 virtual entities at a time in SoA form, with the transcendentals provided by `M`
+#[inline(always)]
+pub fn oscillate_lanes<M: LaneMath>(p: [Vec4; 3], v: [Vec4; 3], t: f32) -> [Vec4; 3] {
+    let speed = M::sqrt(v[0] * v[0] + v[1] * v[1] + v[2] * v[2]);
+    let phase = speed * t;
+    let damping = M::exp(-DAMPING * phase);
+    let (sin, cos) = M::sin_cos(phase);
+
+    let scale = t * damping;
+    [
+        p[0] + scale * cos * v[0],
+        p[1] + scale * sin * v[1],
+        p[2] + scale * v[2],
+    ]
+}
+
+//oscillate_lanes over N-wide std::simd lanes, 4 lanes at a time since `M` works on glam Vec4s
+#[inline(always)]
+pub fn oscillate_simd<M: LaneMath, const N: usize>(
+    p: [Simd<f32, N>; 3],
+    v: [Simd<f32, N>; 3],
+    t: f32,
+) -> [Simd<f32, N>; 3]
+where
+    LaneCount<N>: SupportedLaneCount,
+{
+    let mut n = p.map(Simd::to_array);
+    for lanes in (0..N).step_by(4).map(|i| i..i + 4) {
+        let ps = p.map(|axis| Vec4::from_slice(&axis[lanes.clone()]));
+        let vs = v.map(|axis| Vec4::from_slice(&axis[lanes.clone()]));
+        for (axis, result) in n.iter_mut().zip(oscillate_lanes::<M>(ps, vs, t)) {
+            result.write_to_slice(&mut axis[lanes.clone()]);
+        }
+    }
+    n.map(Simd::from_array)
+}
+
+pub trait LaneMath: 'static {
+    const NAME: &'static str;
+
+    fn sqrt(x: Vec4) -> Vec4;
+    fn exp(x: Vec4) -> Vec4;
+    fn sin_cos(x: Vec4) -> (Vec4, Vec4);
+}
+
+//Data stays in glam vectors, but glam has no vectorized transcendentals, so each lane goes through libm.
+//This is what the kernels look like when written naively against glam.
+pub struct Glam;
+
+impl LaneMath for Glam {
+    const NAME: &'static str = "glam";
+
+    #[inline(always)]
+    fn sqrt(x: Vec4) -> Vec4 {
+        Vec4::from_array(x.to_array().map(f32::sqrt))
+    }
+
+    #[inline(always)]
+    fn exp(x: Vec4) -> Vec4 {
+        Vec4::from_array(x.to_array().map(f32::exp))
+    }
+
+    #[inline(always)]
+    fn sin_cos(x: Vec4) -> (Vec4, Vec4) {
+        let [s0, s1, s2, s3] = x.to_array().map(f32::sin);
+        let [c0, c1, c2, c3] = x.to_array().map(f32::cos);
+        (Vec4::new(s0, s1, s2, s3), Vec4::new(c0, c1, c2, c3))
+    }
+}
+
+//Lane-wise polynomial approximations, vectorized end to end.  Over the ranges this workload produces
+//(phase from 0 to |v| <= sqrt(3) * INPUT_RANGE) exp is within 2 ulps of libm, and sin and cos within 2
+//ulps wherever they're at least 1/4 and within f32::EPSILON absolutely where they cross zero.  Not a
+//general purpose replacement for libm.
+pub struct Polynomial;
+
+const FRAC_2_PI: f32 = std::f32::consts::FRAC_2_PI;
+//pi/2 split in two so the range reduction doesn't lose the low bits
+const FRAC_PI_2_HI: f32 = 1.570_796_4;
+const FRAC_PI_2_LO: f32 = -4.371_139e-8;
+
+const LN_2_HI: f32 = 0.693_145_75;
+const LN_2_LO: f32 = 1.428_606_8e-6;
+
+impl LaneMath for Polynomial {
+    const NAME: &'static str = "polynomial";
+
+    #[inline(always)]
+    fn sqrt(x: Vec4) -> Vec4 {
+        //sqrtps is already lane-wise, nothing to approximate
+        from_simd(to_simd(x).sqrt())
+    }
+
+    #[inline(always)]
+    fn exp(x: Vec4) -> Vec4 {
+        let x = to_simd(x).simd_clamp(f32x4::splat(-87.0), f32x4::splat(87.0));
+
+        //exp(x) = 2^n * exp(r), |r| <= ln(2) / 2
+        let n = (x * f32x4::splat(std::f32::consts::LOG2_E)).round();
+        let r = x - n * f32x4::splat(LN_2_HI) - n * f32x4::splat(LN_2_LO);
+
+        let mut p = f32x4::splat(1.0 / 5040.0);
+        p = p.mul_add(r, f32x4::splat(1.0 / 720.0));
+        p = p.mul_add(r, f32x4::splat(1.0 / 120.0));
+        p = p.mul_add(r, f32x4::splat(1.0 / 24.0));
+        p = p.mul_add(r, f32x4::splat(1.0 / 6.0));
+        p = p.mul_add(r, f32x4::splat(0.5));
+        p = p.mul_add(r, f32x4::splat(1.0));
+        p = p.mul_add(r, f32x4::splat(1.0));
+
+        let scale = f32x4::from_bits(((n.cast::<i32>() + i32x4::splat(127)) << 23).cast::<u32>());
+        from_simd(p * scale)
+    }
+
+    #[inline(always)]
+    fn sin_cos(x: Vec4) -> (Vec4, Vec4) {
+        let x = to_simd(x);
+
+        //x = k * pi/2 + r, |r| <= pi/4.  Fused, so r keeps its low bits where x is near a multiple of
+        //pi/2 and sin or cos is near zero.
+        let k = (x * f32x4::splat(FRAC_2_PI)).round();
+        let r = (-k).mul_add(f32x4::splat(FRAC_PI_2_HI), x);
+        let r = (-k).mul_add(f32x4::splat(FRAC_PI_2_LO), r);
+        let r2 = r * r;
+
+        let mut s = f32x4::splat(1.0 / 362880.0);
+        s = s.mul_add(r2, f32x4::splat(-1.0 / 5040.0));
+        s = s.mul_add(r2, f32x4::splat(1.0 / 120.0));
+        s = s.mul_add(r2, f32x4::splat(-1.0 / 6.0));
+        s = (s * r2).mul_add(r, r);
+
+        let mut c = f32x4::splat(-1.0 / 3628800.0);
+        c = c.mul_add(r2, f32x4::splat(1.0 / 40320.0));
+        c = c.mul_add(r2, f32x4::splat(-1.0 / 720.0));
+        c = c.mul_add(r2, f32x4::splat(1.0 / 24.0));
+        c = c.mul_add(r2, f32x4::splat(-0.5));
+        c = c.mul_add(r2, f32x4::splat(1.0));
+
+        //Quadrant k mod 4 picks which of +-sin(r), +-cos(r) each result is
+        let quadrant = k.cast::<i32>() & i32x4::splat(3);
+        let swap = (quadrant & i32x4::splat(1)).simd_eq(i32x4::splat(1));
+        let negate_sin = (quadrant & i32x4::splat(2)).simd_eq(i32x4::splat(2));
+        let negate_cos = ((quadrant + i32x4::splat(1)) & i32x4::splat(2)).simd_eq(i32x4::splat(2));
+
+        let sin = swap.select(c, s);
+        let cos = swap.select(s, c);
+        let sin = negate_sin.select(-sin, sin);
+        let cos = negate_cos.select(-cos, cos);
+
+        (from_simd(sin), from_simd(cos))
+    }
+}
+
+#[inline(always)]
+fn to_simd(x: Vec4) -> f32x4 {
+    f32x4::from_array(x.to_array())
+}
+
+#[inline(always)]
+fn from_simd(x: f32x4) -> Vec4 {
+    Vec4::from_array(x.to_array())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::inputs::INPUT_RANGE;
+    use super::*;
+
+    //Largest phase the workload produces, with time steps up to 1
+    const MAX_PHASE: f32 = 1.732_050_8 * INPUT_RANGE;
+
+    //Evenly spaced samples over the range, 4 at a time
+    fn sweep(start: f32, end: f32) -> impl Iterator<Item = Vec4> {
+        const SAMPLES: i32 = 1 << 18;
+        (0..SAMPLES / 4).map(move |i| {
+            Vec4::from_array(
+                [0, 1, 2, 3]
+                    .map(|lane| start + (end - start) * (4 * i + lane) as f32 / SAMPLES as f32),
+            )
+        })
+    }
+
+    //Only meaningful for values of the same sign
+    fn ulps(a: f32, b: f32) -> u64 {
+        (a.to_bits() as i64 - b.to_bits() as i64).unsigned_abs()
+    }
+
+    #[test]
+    fn polynomial_exp_is_within_2_ulps() {
+        for x in sweep(-DAMPING * MAX_PHASE, 0.0) {
+            let exp = Polynomial::exp(x);
+            for lane in 0..4 {
+                let expected = (x[lane] as f64).exp() as f32;
+                assert!(
+                    ulps(exp[lane], expected) <= 2,
+                    "exp({}) = {}",
+                    x[lane],
+                    exp[lane]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn polynomial_sin_cos_are_within_2_ulps() {
+        for x in sweep(0.0, MAX_PHASE) {
+            let (sin, cos) = Polynomial::sin_cos(x);
+            for lane in 0..4 {
+                let x = x[lane] as f64;
+                for (name, value, expected) in [
+                    ("sin", sin[lane], x.sin() as f32),
+                    ("cos", cos[lane], x.cos() as f32),
+                ] {
+                    if expected.abs() >= 0.25 {
+                        assert!(ulps(value, expected) <= 2, "{}({}) = {}", name, x, value);
+                    } else {
+                        assert!(
+                            (value - expected).abs() <= f32::EPSILON,
+                            "{}({}) = {}",
+                            name,
+                            x,
+                            value
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
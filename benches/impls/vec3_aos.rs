@@ -0,0 +1,76 @@
+use bevy::prelude::*;
+
+use super::inputs::InputDistribution;
+use super::traffic::Traffic;
+use super::transcendental::oscillate_scalar;
+
+//Plain AoS layout, one Vec3 per component.  It's what the migration benchmarks convert to and from a
+//packed layout, and what the AoS gather looks up parents in.  Components are public so the packed
+//layouts can read and restore them.
+
+#[derive(Component)]
+pub struct Position(pub Vec3);
+
+#[derive(Component)]
+pub struct Velocity(pub Vec3);
+
+pub struct Benchmark<'w>(World, QueryState<(&'w Velocity, &'w mut Position)>);
+
+//Bytes touched per virtual entity by the update kernel
+pub fn traffic() -> Traffic {
+    Traffic::update::<Velocity, Position>(1)
+}
+
+impl<'w> Benchmark<'w> {
+    pub fn new(size: i32) -> Self {
+        let inputs = InputDistribution::default();
+        let mut rng = rand::thread_rng();
+
+        let mut world = World::new();
+
+        world.spawn_batch((0..size).map(|_| {
+            (
+                Position(inputs.position3(&mut rng)),
+                Velocity(inputs.velocity3(&mut rng)),
+            )
+        }));
+
+        Self::from_world(world)
+    }
+
+    pub fn from_world(mut world: World) -> Self {
+        let query = world.query::<(&Velocity, &mut Position)>();
+        Self(world, query)
+    }
+
+    pub fn into_world(self) -> World {
+        self.0
+    }
+
+    //Every entity's position, for checking a conversion round trip
+    pub fn positions(&mut self) -> Vec<(Entity, Vec3)> {
+        self.0
+            .query::<(Entity, &Position)>()
+            .iter(&self.0)
+            .map(|(entity, position)| (entity, position.0))
+            .collect()
+    }
+
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run(&mut self, time: f32) {
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                position.0 += time * velocity.0;
+            });
+    }
+
+    //Transcendental workload through libm.  One virtual entity per entity, so there are no lanes for
+    //the vectorized forms to fill.
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_oscillator_libm(&mut self, time: f32) {
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                position.0 = oscillate_scalar(position.0, velocity.0, time);
+            });
+    }
+}
@@ -0,0 +1,249 @@
+use bevy::ecs::component::TableStorage;
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use std::marker::PhantomData;
+use std::mem::size_of;
+
+use super::changes::{ChangeBenchmark, Lanes};
+use super::footprint::Footprint;
+use super::inputs::InputDistribution;
+use super::lifecycle::{self, Operation, Prepared};
+use super::quantize::{max_error_time_step, ErrorStats, Quantization};
+use super::setup::Setup;
+use super::storage::Storage;
+use super::system::{SystemBenchmark, TimeStep};
+use super::traffic::Traffic;
+use super::transcendental::oscillate_scalar;
+
+//AoS layout with each Vec3 quantized to 3 16 bit words (6 bytes instead of 12)
+
+pub struct Position<Q: Quantization, S: Storage = TableStorage>([u16; 3], PhantomData<(Q, S)>);
+
+impl<Q: Quantization, S: Storage> Component for Position<Q, S> {
+    type Storage = S;
+}
+
+pub struct Velocity<Q: Quantization, S: Storage = TableStorage>([u16; 3], PhantomData<(Q, S)>);
+
+impl<Q: Quantization, S: Storage> Component for Velocity<Q, S> {
+    type Storage = S;
+}
+
+#[inline(always)]
+fn encode<Q: Quantization>(v: Vec3) -> [u16; 3] {
+    v.to_array().map(Q::encode)
+}
+
+#[inline(always)]
+fn decode<Q: Quantization>(v: [u16; 3]) -> Vec3 {
+    Vec3::from_array(v.map(Q::decode))
+}
+
+pub struct Benchmark<'w, Q: Quantization, S: Storage = TableStorage>(
+    World,
+    QueryState<(&'w Velocity<Q, S>, &'w mut Position<Q, S>)>,
+);
+
+//Bytes touched per virtual entity by the update kernel
+pub fn traffic<Q: Quantization>() -> Traffic {
+    Traffic::update::<Velocity<Q>, Position<Q>>(1)
+}
+
+//Memory the world holds for this layout.  Each value is a single u16.
+pub fn footprint<Q: Quantization>(size: i32) -> Footprint {
+    Footprint::of(&Benchmark::<Q>::new(size).0, 1, 6 * size_of::<u16>())
+}
+
+//Spawning, inserting into or removing from `size` virtual entities of this layout, see lifecycle.rs
+pub fn lifecycle<Q: Quantization>(size: i32, operation: Operation) -> Prepared {
+    lifecycle::prepare(
+        size as usize,
+        operation,
+        |inputs, rng| {
+            moving_bundle::<Q, TableStorage>(inputs.position3(rng), inputs.velocity3(rng))
+        },
+        |inputs, rng| position_bundle::<Q, TableStorage>(inputs.position3(rng)),
+        |inputs, rng| velocity_bundle::<Q, TableStorage>(inputs.velocity3(rng)),
+    )
+}
+
+fn moving_bundle<Q: Quantization, S: Storage>(
+    p: Vec3,
+    v: Vec3,
+) -> (Position<Q, S>, Velocity<Q, S>) {
+    let (position,) = position_bundle(p);
+    let (velocity,) = velocity_bundle(v);
+    (position, velocity)
+}
+
+fn position_bundle<Q: Quantization, S: Storage>(p: Vec3) -> (Position<Q, S>,) {
+    (Position(encode::<Q>(p), PhantomData),)
+}
+
+fn velocity_bundle<Q: Quantization, S: Storage>(v: Vec3) -> (Velocity<Q, S>,) {
+    (Velocity(encode::<Q>(v), PhantomData),)
+}
+
+impl<'w, Q: Quantization> Benchmark<'w, Q> {
+    pub fn new(size: i32) -> Self {
+        Self::with_storage(size)
+    }
+
+    //The world spawned from `setup.inputs`, then passed through `setup.transform`
+    pub fn with_setup(size: i32, setup: &Setup) -> Self {
+        let mut bench = Self::from_values(&random_values(size, &setup.inputs));
+        bench.0 = (setup.transform)(std::mem::take(&mut bench.0));
+        bench
+    }
+}
+
+impl<'w, Q: Quantization, S: Storage> Benchmark<'w, Q, S> {
+    pub fn with_storage(size: i32) -> Self {
+        Self::from_values(&random_values(size, &InputDistribution::default()))
+    }
+
+    fn from_values(values: &[(Vec3, Vec3)]) -> Self {
+        let mut world = World::new();
+
+        world.spawn_batch(values.iter().map(|&(p, v)| moving_bundle::<Q, S>(p, v)));
+
+        let query = world.query::<(&Velocity<Q, S>, &mut Position<Q, S>)>();
+        Self(world, query)
+    }
+
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run(&mut self, time: f32) {
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                update(time, velocity, &mut position)
+            });
+    }
+
+    //Same as run, split over the ComputeTaskPool in tasks of `batch_size` entities
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_par(&mut self, time: f32, batch_size: usize) {
+        self.1
+            .par_for_each_mut(&mut self.0, batch_size, |(velocity, mut position)| {
+                update(time, velocity, &mut position)
+            });
+    }
+
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_nochange(&mut self, time: f32) {
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                update(time, velocity, position.bypass_change_detection())
+            });
+    }
+
+    //Transcendental workload through libm.  One virtual entity per entity, so there are no lanes for
+    //the vectorized forms to fill.
+    #[cfg_attr(feature = "audit", inline(never))]
+    pub fn run_oscillator_libm(&mut self, time: f32) {
+        self.1
+            .for_each_mut(&mut self.0, |(velocity, mut position)| {
+                let p = decode::<Q>(position.0);
+                let v = decode::<Q>(velocity.0);
+                position.0 = encode::<Q>(oscillate_scalar(p, v, time));
+            });
+    }
+}
+
+fn random_values(size: i32, inputs: &InputDistribution) -> Vec<(Vec3, Vec3)> {
+    let mut rng = rand::thread_rng();
+
+    (0..size)
+        .map(|_| (inputs.position3(&mut rng), inputs.velocity3(&mut rng)))
+        .collect()
+}
+
+//Runs the quantized layout and a plain f32 reference over the same inputs for `frames` updates
+//and compares the resulting positions component by component
+pub fn error_stats<Q: Quantization>(size: i32, frames: usize) -> ErrorStats {
+    let values = random_values(size, &InputDistribution::default());
+    let mut bench = Benchmark::<Q>::from_values(&values);
+    let mut reference: Vec<(Vec3, Vec3)> = values;
+
+    let mut rng = rand::thread_rng();
+
+    //Kept small enough that no position leaves the snorm16 range
+    let max_time = max_error_time_step(frames);
+
+    for _ in 0..frames {
+        let time = rng.gen_range(0.0..=max_time);
+        bench.run(time);
+        for (p, v) in reference.iter_mut() {
+            *p += time * *v;
+        }
+    }
+
+    //spawn_batch into a fresh World hands out entity indices in spawn order
+    let mut query = bench.0.query::<(Entity, &Position<Q>)>();
+    let results: Vec<(u32, Vec3)> = query
+        .iter(&bench.0)
+        .map(|(entity, position)| (entity.id(), decode::<Q>(position.0)))
+        .collect();
+
+    ErrorStats::from_pairs(results.into_iter().flat_map(|(id, quantized)| {
+        let expected = reference[id as usize].0;
+        expected.to_array().into_iter().zip(quantized.to_array())
+    }))
+}
+
+//The update kernel of run, run_par, run_nochange and the system
+#[inline(always)]
+fn update<Q: Quantization, S: Storage>(
+    time: f32,
+    velocity: &Velocity<Q, S>,
+    position: &mut Position<Q, S>,
+) {
+    let p = decode::<Q>(position.0);
+    let v = decode::<Q>(velocity.0);
+    position.0 = encode::<Q>(p + time * v);
+}
+
+//The update kernel as a regular system
+fn update_system<Q: Quantization>(
+    time: Res<TimeStep>,
+    mut query: Query<(&Velocity<Q>, &mut Position<Q>)>,
+) {
+    let time = time.0;
+    query.for_each_mut(|(velocity, mut position)| update(time, velocity, &mut position));
+}
+
+pub fn system_benchmark<Q: Quantization>(size: i32) -> SystemBenchmark {
+    SystemBenchmark::new(Benchmark::<Q>::new(size).0, update_system::<Q>)
+}
+
+impl<Q: Quantization> Lanes<Position<Q>> for Velocity<Q> {
+    const LANES: usize = 1;
+
+    #[inline(always)]
+    fn update(time: f32, velocity: &Self, position: &mut Position<Q>) {
+        update(time, velocity, position)
+    }
+
+    #[inline(always)]
+    fn moving(&self) -> bool {
+        self.0 != encode::<Q>(Vec3::ZERO)
+    }
+
+    fn stop(&mut self, _lane: usize) {
+        self.0 = encode::<Q>(Vec3::ZERO);
+    }
+
+    #[inline(always)]
+    fn read(position: &Position<Q>) -> f32 {
+        decode::<Q>(position.0).dot(Vec3::ONE)
+    }
+}
+
+//Change detection benchmark: stationary entities and a `Changed<Position>` consumer.  One virtual
+//entity per entity, so this is the baseline the packed layouts' coarser change marking is read against.
+pub fn change_benchmark<Q: Quantization>(
+    size: i32,
+    stationary_fraction: f64,
+) -> ChangeBenchmark<Position<Q>, Velocity<Q>> {
+    ChangeBenchmark::new(Benchmark::<Q>::new(size).0, stationary_fraction)
+}
@@ -0,0 +1,437 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub const USAGE: &str = "\
+usage: bevy-simd-bench audit [options]
+
+audit options:
+  --binary <file>     binary to disassemble (default: newest target/release/deps/bench-*)
+  --objdump <file>    objdump to use (default: objdump)
+  --layout <module>   only audit this layout module, may be repeated (default: all)";
+
+const DEFAULT_DEPS_DIR: &str = "target/release/deps";
+const BENCH_BINARY_PREFIX: &str = "bench-";
+
+//Layout modules with kernels to audit, and whether they're meant to compile to packed code.  Plain
+//`Vec3` and the scalar SoA loops are the scalar baselines; everything else is supposed to be SIMD.
+const LAYOUTS: &[(&str, bool)] = &[
+    ("aosoa_portable", true),
+    ("aosoa_sse4", true),
+    ("aosoa_sse4_quantized", true),
+    ("naive_batched_swizzle", true),
+    ("simd_batch_portable", true),
+    ("simd_batch_sse4", true),
+    ("soa", false),
+    ("soa_columns", false),
+    ("vec3", false),
+    ("vec3_aligned", true),
+    ("vec3_quantized", false),
+];
+
+//Kernels going lane by lane through libm are scalar on purpose
+const SCALAR_KERNEL_SUFFIX: &str = "_libm";
+
+const ARITHMETIC: &[&str] = &[
+    "add",
+    "sub",
+    "mul",
+    "div",
+    "sqrt",
+    "min",
+    "max",
+    "rcp",
+    "rsqrt",
+    "fmadd132",
+    "fmadd213",
+    "fmadd231",
+    "fmsub132",
+    "fmsub213",
+    "fmsub231",
+    "fnmadd132",
+    "fnmadd213",
+    "fnmadd231",
+    "fnmsub132",
+    "fnmsub213",
+    "fnmsub231",
+    //Conversions ending in a float suffix: cvtdq2ps, cvtph2ps, cvtps2pd, cvtss2sd, cvtsi2ss, ...
+    "cvtdq2",
+    "cvtph2",
+    "cvtps2",
+    "cvtpd2",
+    "cvtss2",
+    "cvtsd2",
+    "cvtsi2",
+];
+
+//Packed instructions that don't end in a float suffix: conversions to integers or halves, and the
+//packed integer ops the quantized layouts encode and decode with
+const PACKED_OTHER: &[&str] = &[
+    "cvtps2dq",
+    "cvttps2dq",
+    "cvtpd2dq",
+    "cvttpd2dq",
+    "cvtps2ph",
+    "paddb",
+    "paddw",
+    "paddd",
+    "paddq",
+    "psubb",
+    "psubw",
+    "psubd",
+    "psubq",
+    "pmullw",
+    "pmulld",
+    "pmulhw",
+    "pmuludq",
+    "pmaddwd",
+    "pand",
+    "pandn",
+    "por",
+    "pxor",
+    "pminsw",
+    "pmaxsw",
+    "pminsd",
+    "pmaxsd",
+    "pminud",
+    "pmaxud",
+    "psllw",
+    "pslld",
+    "psllq",
+    "psrlw",
+    "psrld",
+    "psrlq",
+    "psraw",
+    "psrad",
+    "pcmpeqw",
+    "pcmpeqd",
+    "pcmpgtw",
+    "pcmpgtd",
+    "packssdw",
+    "packusdw",
+    "pmovsxwd",
+    "pmovzxwd",
+];
+
+//Scalar conversions that don't end in a float suffix, with and without objdump's operand size suffix
+const SCALAR_OTHER: &[&str] = &[
+    "cvtss2si",
+    "cvttss2si",
+    "cvtsd2si",
+    "cvttsd2si",
+    "cvtsi2ssl",
+    "cvtsi2ssq",
+    "cvtsi2sdl",
+    "cvtsi2sdq",
+];
+
+const SHUFFLES: &[&str] = &[
+    "shufps",
+    "shufpd",
+    "unpcklps",
+    "unpckhps",
+    "unpcklpd",
+    "unpckhpd",
+    "movlhps",
+    "movhlps",
+    "movsldup",
+    "movshdup",
+    "movddup",
+    "insertps",
+    "extractps",
+    "blendps",
+    "blendpd",
+    "blendvps",
+    "blendvpd",
+    "pshufd",
+    "pshufb",
+    "pshuflw",
+    "pshufhw",
+    "permilps",
+    "permilpd",
+    "permps",
+    "permpd",
+    "permd",
+    "perm2f128",
+    "broadcastss",
+    "broadcastsd",
+    "insertf128",
+    "extractf128",
+    "pinsrd",
+    "pextrd",
+    "pblendw",
+    "palignr",
+];
+
+pub struct AuditOptions {
+    pub binary: Option<PathBuf>,
+    pub objdump: String,
+    pub layouts: Vec<String>,
+}
+
+impl AuditOptions {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            binary: None,
+            objdump: "objdump".to_string(),
+            layouts: Vec::new(),
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {}", arg))
+            };
+
+            match arg.as_str() {
+                "--binary" => options.binary = Some(value()?.into()),
+                "--objdump" => options.objdump = value()?,
+                "--layout" => options.layouts.push(value()?),
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+
+        if let Some(layout) = options
+            .layouts
+            .iter()
+            .find(|layout| !LAYOUTS.iter().any(|(known, _)| known == layout))
+        {
+            return Err(format!("unknown layout module {}", layout));
+        }
+
+        Ok(options)
+    }
+}
+
+//Instruction mix of one kernel
+#[derive(Debug, Default)]
+pub struct InstructionCounts {
+    pub instructions: usize,
+    pub packed: usize,
+    pub scalar: usize,
+    pub shuffles: usize,
+    //Packed instructions on ymm or zmm registers
+    pub wide: usize,
+    pub calls: usize,
+}
+
+impl InstructionCounts {
+    fn add(&mut self, mnemonic: &str, operands: &str) {
+        self.instructions += 1;
+
+        if mnemonic.starts_with("call") {
+            self.calls += 1;
+            return;
+        }
+
+        //AVX encodings are the SSE mnemonic with a `v` in front
+        let base = mnemonic.strip_prefix('v').unwrap_or(mnemonic);
+
+        if SHUFFLES.contains(&base) {
+            self.shuffles += 1;
+            return;
+        }
+
+        if PACKED_OTHER.contains(&base) {
+            self.add_packed(operands);
+            return;
+        }
+        if SCALAR_OTHER.contains(&base) {
+            self.scalar += 1;
+            return;
+        }
+
+        let (op, suffix) = base.split_at(base.len().saturating_sub(2));
+        if !ARITHMETIC.contains(&op) {
+            return;
+        }
+
+        match suffix {
+            "ps" | "pd" => self.add_packed(operands),
+            "ss" | "sd" => self.scalar += 1,
+            _ => {}
+        }
+    }
+
+    fn add_packed(&mut self, operands: &str) {
+        self.packed += 1;
+        if operands.contains("ymm") || operands.contains("zmm") {
+            self.wide += 1;
+        }
+    }
+
+    //Most of the float math is packed
+    pub fn is_vectorized(&self) -> bool {
+        self.packed > 0 && self.packed >= self.scalar
+    }
+}
+
+pub fn audit(options: &AuditOptions) -> Result<(), String> {
+    let binary = match &options.binary {
+        Some(binary) => binary.clone(),
+        None => newest_bench_binary(Path::new(DEFAULT_DEPS_DIR))?,
+    };
+
+    let output = Command::new(&options.objdump)
+        .args(["-d", "--demangle", "--no-show-raw-insn"])
+        .arg(&binary)
+        .output()
+        .map_err(|e| format!("couldn't run {}: {}", options.objdump, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed on {}: {}",
+            options.objdump,
+            binary.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let disassembly = String::from_utf8_lossy(&output.stdout);
+    let kernels = find_kernels(&disassembly, &options.layouts);
+
+    println!("auditing {}", binary.display());
+    println!(
+        "{:<90} {:>7} {:>7} {:>7} {:>8} {:>6} {:>6}",
+        "kernel", "insns", "packed", "scalar", "shuffles", "wide", "calls"
+    );
+
+    let mut flagged = Vec::new();
+    for ((layout, name), counts) in &kernels {
+        let expected = expects_vectorized(layout, name);
+        let flag = if expected && !counts.is_vectorized() {
+            flagged.push(name);
+            "  <- scalar"
+        } else {
+            ""
+        };
+
+        println!(
+            "{:<90} {:>7} {:>7} {:>7} {:>8} {:>6} {:>6}{}",
+            name,
+            counts.instructions,
+            counts.packed,
+            counts.scalar,
+            counts.shuffles,
+            counts.wide,
+            counts.calls,
+            flag
+        );
+    }
+
+    //Kernels inlined into their caller have no symbol of their own
+    for (layout, _) in LAYOUTS
+        .iter()
+        .filter(|(layout, _)| selected(&options.layouts, layout))
+    {
+        if !kernels.keys().any(|(found, _)| found == layout) {
+            println!(
+                "{}: no kernel symbols found, was the bench built with --features audit?",
+                layout
+            );
+        }
+    }
+
+    if flagged.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} kernel(s) compiled to mostly scalar code",
+            flagged.len()
+        ))
+    }
+}
+
+fn selected(layouts: &[String], layout: &str) -> bool {
+    layouts.is_empty() || layouts.iter().any(|selected| selected == layout)
+}
+
+fn expects_vectorized(layout: &str, kernel: &str) -> bool {
+    let packed_layout = LAYOUTS
+        .iter()
+        .find(|(known, _)| *known == layout)
+        .map_or(false, |(_, packed)| *packed);
+
+    //Drop any generic arguments and the closure path to get at the method name
+    let method = kernel
+        .split("::")
+        .find(|segment| segment.starts_with("run"))
+        .unwrap_or(kernel);
+    let method = method.split('<').next().unwrap_or(method);
+
+    packed_layout && !method.ends_with(SCALAR_KERNEL_SUFFIX)
+}
+
+//Counts instructions in every function belonging to a layout's `run*` kernels, keyed by layout and
+//demangled symbol name
+fn find_kernels(
+    disassembly: &str,
+    layouts: &[String],
+) -> BTreeMap<(&'static str, String), InstructionCounts> {
+    let mut kernels = BTreeMap::new();
+    let mut current: Option<(&'static str, String)> = None;
+
+    for line in disassembly.lines() {
+        //Function headers look like `0000000000012340 <bench::impls::aosoa_sse4::Benchmark::run>:`
+        if let Some(name) = line
+            .strip_suffix(">:")
+            .and_then(|line| line.split_once(" <"))
+            .map(|(_, name)| name)
+        {
+            current = kernel_layout(name)
+                .filter(|layout| selected(layouts, layout))
+                .map(|layout| (layout, name.to_string()));
+            continue;
+        }
+
+        //Instructions look like `   12340:\tvaddps %xmm1,%xmm0,%xmm0`
+        let (Some(kernel), Some((_, instruction))) = (&current, line.split_once(":\t")) else {
+            continue;
+        };
+
+        let mut parts = instruction.split_whitespace();
+        let Some(mnemonic) = parts.next() else {
+            continue;
+        };
+        let operands = parts.collect::<Vec<_>>().join(" ");
+
+        kernels
+            .entry(kernel.clone())
+            .or_insert_with(InstructionCounts::default)
+            .add(mnemonic, &operands);
+    }
+
+    kernels
+}
+
+//The layout module a symbol belongs to, if it's one of its kernels
+fn kernel_layout(name: &str) -> Option<&'static str> {
+    LAYOUTS.iter().map(|(layout, _)| *layout).find(|layout| {
+        name.split_once(&format!("impls::{}::", layout))
+            .map_or(false, |(_, rest)| rest.contains("::run"))
+    })
+}
+
+//cargo leaves every build of the bench in deps; the newest one is the one just built
+fn newest_bench_binary(deps: &Path) -> Result<PathBuf, String> {
+    let entries =
+        fs::read_dir(deps).map_err(|e| format!("couldn't read {}: {}", deps.display(), e))?;
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            //Skip the .d dependency files and anything else with an extension
+            name.starts_with(BENCH_BINARY_PREFIX) && !name.contains('.')
+        })
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .max()
+        .map(|(_, path)| path)
+        .ok_or_else(|| {
+            format!(
+                "no bench binary in {}, run `cargo bench --no-run --features audit` first",
+                deps.display()
+            )
+        })
+}
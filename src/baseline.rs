@@ -0,0 +1,213 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::criterion_results::{self, Measurement};
+use crate::export;
+use crate::fingerprint::Fingerprint;
+
+pub const USAGE: &str = "\
+usage: bevy-simd-bench baseline save <name> [options]
+       bevy-simd-bench baseline compare <name> [options]
+       bevy-simd-bench baseline list [options]
+
+baseline options:
+  --criterion-dir <dir>   criterion output to read (default: target/criterion)
+  --dir <dir>             where baselines are stored (default: baselines)
+  --threshold <percent>   throughput change reported by compare (default: 5)
+
+compare exits with status 1 if any benchmark regressed by more than the threshold.";
+
+const DEFAULT_DIR: &str = "baselines";
+const DEFAULT_THRESHOLD_PERCENT: f64 = 5.0;
+
+#[derive(Serialize, Deserialize)]
+struct Baseline {
+    cpu: String,
+    measurements: Vec<Measurement>,
+}
+
+enum Command {
+    Save(String),
+    Compare(String),
+    List,
+}
+
+pub struct BaselineOptions {
+    command: Command,
+    criterion_dir: PathBuf,
+    dir: PathBuf,
+    threshold_percent: f64,
+}
+
+impl BaselineOptions {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let command = match args.next().as_deref() {
+            Some("save") => Command::Save(args.next().ok_or("missing baseline name")?),
+            Some("compare") => Command::Compare(args.next().ok_or("missing baseline name")?),
+            Some("list") => Command::List,
+            _ => return Err(USAGE.to_string()),
+        };
+
+        let mut options = Self {
+            command,
+            criterion_dir: criterion_results::DEFAULT_CRITERION_DIR.into(),
+            dir: DEFAULT_DIR.into(),
+            threshold_percent: DEFAULT_THRESHOLD_PERCENT,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {}", arg))
+            };
+
+            match arg.as_str() {
+                "--criterion-dir" => options.criterion_dir = value()?.into(),
+                "--dir" => options.dir = value()?.into(),
+                "--threshold" => {
+                    let threshold = value()?;
+                    options.threshold_percent = threshold
+                        .parse()
+                        .map_err(|_| format!("expected a percentage, got {}", threshold))?;
+                }
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+
+        Ok(options)
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name).with_extension("json")
+    }
+}
+
+pub fn baseline(options: &BaselineOptions) -> Result<(), String> {
+    match &options.command {
+        Command::Save(name) => save(options, name),
+        Command::Compare(name) => compare(options, name),
+        Command::List => list(options),
+    }
+}
+
+fn save(options: &BaselineOptions, name: &str) -> Result<(), String> {
+    let baseline = Baseline {
+        cpu: Fingerprint::collect().cpu,
+        measurements: criterion_results::load(&options.criterion_dir)?,
+    };
+
+    let path = options.path(name);
+    let file = export::create(&path)?;
+    serde_json::to_writer_pretty(file, &baseline)
+        .map_err(|e| format!("couldn't write {}: {}", path.display(), e))?;
+
+    println!(
+        "saved {} results as baseline {} ({})",
+        baseline.measurements.len(),
+        name,
+        path.display()
+    );
+    Ok(())
+}
+
+fn list(options: &BaselineOptions) -> Result<(), String> {
+    let entries = match fs::read_dir(&options.dir) {
+        Ok(entries) => entries,
+        //Nothing saved yet
+        Err(_) => return Ok(()),
+    };
+
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "json"))
+        .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+        .collect();
+    names.sort();
+
+    for name in names {
+        println!("{}", name);
+    }
+    Ok(())
+}
+
+fn compare(options: &BaselineOptions, name: &str) -> Result<(), String> {
+    let path = options.path(name);
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| format!("couldn't read baseline {}: {}", path.display(), e))?;
+    let baseline: Baseline = serde_json::from_str(&contents)
+        .map_err(|e| format!("couldn't parse baseline {}: {}", path.display(), e))?;
+
+    let current = criterion_results::load(&options.criterion_dir)?;
+
+    let cpu = Fingerprint::collect().cpu;
+    if cpu != baseline.cpu {
+        println!(
+            "warning: baseline {} was recorded on {}, this machine is {}\n",
+            name, baseline.cpu, cpu
+        );
+    }
+
+    let key = |m: &Measurement| (m.group.clone(), m.benchmark.clone(), m.size);
+    let before: BTreeMap<_, _> = baseline.measurements.iter().map(|m| (key(m), m)).collect();
+
+    let mut regressions = 0;
+    let mut improvements = 0;
+    let mut unchanged = 0;
+    let mut missing = before.len();
+
+    println!(
+        "{:<24} {:<60} {:>8} {:>14} {:>14} {:>9}",
+        "group", "benchmark", "size", "before Melem/s", "after Melem/s", "change"
+    );
+
+    for m in &current {
+        let old = match before.get(&key(m)) {
+            Some(old) => old,
+            None => continue,
+        };
+        missing -= 1;
+
+        //Positive is faster
+        let change = (m.elements_per_second() / old.elements_per_second() - 1.0) * 100.0;
+
+        let verdict = if change < -options.threshold_percent {
+            regressions += 1;
+            "REGRESSED"
+        } else if change > options.threshold_percent {
+            improvements += 1;
+            "improved"
+        } else {
+            unchanged += 1;
+            continue;
+        };
+
+        println!(
+            "{:<24} {:<60} {:>8} {:>14.1} {:>14.1} {:>+8.1}% {}",
+            m.group,
+            m.benchmark,
+            m.size,
+            old.elements_per_second() / 1e6,
+            m.elements_per_second() / 1e6,
+            change,
+            verdict
+        );
+    }
+
+    println!(
+        "\n{} regressed, {} improved, {} within {}%, {} in the baseline but not rerun",
+        regressions, improvements, unchanged, options.threshold_percent, missing
+    );
+
+    if regressions > 0 {
+        return Err(format!(
+            "{} benchmarks regressed by more than {}% against baseline {}",
+            regressions, options.threshold_percent, name
+        ));
+    }
+
+    Ok(())
+}
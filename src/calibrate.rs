@@ -0,0 +1,175 @@
+use std::fs;
+use std::hint::black_box;
+use std::path::{Path, PathBuf};
+use std::simd::f32x4;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::export;
+use crate::fingerprint::Fingerprint;
+use crate::runner::parse_number;
+
+pub const USAGE: &str = "\
+usage: bevy-simd-bench calibrate [options]
+
+calibrate options:
+  --output <file>       where to store the results (default: target/simd-bench/calibration.json)
+  --array-mib <n>       size of each STREAM array, should be well past the LLC (default: 256)
+  --repeats <n>         passes of each kernel, the best one is kept (default: 10)";
+
+pub const DEFAULT_CALIBRATION: &str = "target/simd-bench/calibration.json";
+const DEFAULT_ARRAY_MIB: usize = 256;
+const DEFAULT_REPEATS: usize = 10;
+
+//Independent accumulators in the peak FLOP loop, enough to hide the add/mul latency
+const ACCUMULATORS: usize = 12;
+const PEAK_ITERATIONS: usize = 50_000_000;
+
+//Single threaded ceilings of this machine, the same setting the benchmarks run in
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Calibration {
+    pub cpu: String,
+    pub copy_gigabytes_per_second: f64,
+    pub triad_gigabytes_per_second: f64,
+    pub peak_gigaflops: f64,
+}
+
+impl Calibration {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| format!("couldn't parse {}: {}", path.display(), e))
+    }
+
+    //Attainable GFLOP/s at the given arithmetic intensity (FLOP per byte).  The kernels read more than
+    //they write, like triad, so that's the bandwidth ceiling used.
+    pub fn roofline(&self, arithmetic_intensity: f64) -> f64 {
+        (arithmetic_intensity * self.triad_gigabytes_per_second).min(self.peak_gigaflops)
+    }
+}
+
+pub struct CalibrateOptions {
+    pub output: PathBuf,
+    pub array_mib: usize,
+    pub repeats: usize,
+}
+
+impl CalibrateOptions {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            output: DEFAULT_CALIBRATION.into(),
+            array_mib: DEFAULT_ARRAY_MIB,
+            repeats: DEFAULT_REPEATS,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {}", arg))
+            };
+
+            match arg.as_str() {
+                "--output" => options.output = value()?.into(),
+                "--array-mib" => options.array_mib = parse_number(&value()?)?,
+                "--repeats" => options.repeats = parse_number(&value()?)?,
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+
+        if options.array_mib == 0 || options.repeats == 0 {
+            return Err("--array-mib and --repeats must be at least 1".to_string());
+        }
+
+        Ok(options)
+    }
+}
+
+pub fn calibrate(options: &CalibrateOptions) -> Result<(), String> {
+    let len = options.array_mib * 1024 * 1024 / std::mem::size_of::<f32>();
+
+    let copy = stream_copy(len, options.repeats);
+    println!("copy:  {:.2} GB/s", copy);
+
+    let triad = stream_triad(len, options.repeats);
+    println!("triad: {:.2} GB/s", triad);
+
+    let peak = best_of(options.repeats, peak_flops);
+    println!("peak:  {:.2} GFLOP/s", peak);
+
+    let calibration = Calibration {
+        cpu: Fingerprint::collect().cpu,
+        copy_gigabytes_per_second: copy,
+        triad_gigabytes_per_second: triad,
+        peak_gigaflops: peak,
+    };
+
+    let file = export::create(&options.output)?;
+    serde_json::to_writer_pretty(file, &calibration)
+        .map_err(|e| format!("couldn't write {}: {}", options.output.display(), e))?;
+
+    println!("saved to {}", options.output.display());
+    Ok(())
+}
+
+fn best_of(repeats: usize, mut run: impl FnMut() -> f64) -> f64 {
+    (0..repeats).map(|_| run()).fold(0.0, f64::max)
+}
+
+//a[i] = b[i], in GB/s counting the bytes read and written.  Every array is filled before timing so
+//the first pass doesn't pay for faulting in fresh zero pages, and the best of `passes` passes is kept.
+fn stream_copy(len: usize, passes: usize) -> f64 {
+    let b = vec![1.0f32; len];
+    let mut a = vec![2.0f32; len];
+
+    let bytes = 2 * len * std::mem::size_of::<f32>();
+    best_of(passes, || {
+        let start = Instant::now();
+        a.copy_from_slice(black_box(&b));
+        let elapsed = start.elapsed();
+        black_box(&a);
+
+        bytes as f64 / elapsed.as_nanos() as f64
+    })
+}
+
+//a[i] = b[i] + s * c[i], in GB/s counting the bytes read and written, prepared like stream_copy
+fn stream_triad(len: usize, passes: usize) -> f64 {
+    let b = vec![1.0f32; len];
+    let c = vec![2.0f32; len];
+    let mut a = vec![3.0f32; len];
+    let s = black_box(3.0f32);
+
+    let bytes = 3 * len * std::mem::size_of::<f32>();
+    best_of(passes, || {
+        let start = Instant::now();
+        for ((a, b), c) in a.iter_mut().zip(black_box(&b)).zip(black_box(&c)) {
+            *a = b + s * c;
+        }
+        let elapsed = start.elapsed();
+        black_box(&a);
+
+        bytes as f64 / elapsed.as_nanos() as f64
+    })
+}
+
+//Separate mul and add on 4-wide vectors, the instruction mix the kernels compile to without fast-math
+//or FMA target features.  In GFLOP/s.
+fn peak_flops() -> f64 {
+    let mut acc = [f32x4::splat(1.0); ACCUMULATORS];
+    let m = black_box(f32x4::splat(0.999_999));
+    let a = black_box(f32x4::splat(1e-7));
+
+    let start = Instant::now();
+    for _ in 0..PEAK_ITERATIONS {
+        for acc in acc.iter_mut() {
+            *acc = *acc * m + a;
+        }
+    }
+    let elapsed = start.elapsed();
+    black_box(&acc);
+
+    let flops = PEAK_ITERATIONS * ACCUMULATORS * 4 * 2;
+    flops as f64 / elapsed.as_nanos() as f64
+}
@@ -0,0 +1,112 @@
+use crate::impls::changes::STATIONARY_FRACTIONS;
+use crate::impls::registry::{ChangeKernel, CHANGE_CASES};
+use crate::runner::{time_frames, SweepOptions};
+use crate::stats::FrameStats;
+
+pub const USAGE: &str = "\
+usage: bevy-simd-bench changes [options]
+
+changes options:
+  --layout <name>       layout to run, may be repeated (default: all)
+  --size <n>            number of virtual entities, may be repeated (default: 16383 32767)
+  --stationary <f>      fraction of virtual entities with zero velocity, may be repeated
+                        (default: 0 0.5 0.9 0.99)
+  --frames <n>          timed frames per case (default: 1000)
+  --warmup <n>          untimed frames run before timing (default: 100)";
+
+pub struct ChangesOptions {
+    pub sweep: SweepOptions,
+    pub stationary_fractions: Vec<f64>,
+}
+
+impl ChangesOptions {
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let (sweep, [mut stationary_fractions]) = SweepOptions::parse(args, ["--stationary"])?;
+
+        sweep.check_layouts(
+            |layout| CHANGE_CASES.iter().any(|case| case.layout == layout),
+            "change detection",
+        )?;
+        if stationary_fractions.is_empty() {
+            stationary_fractions.extend(STATIONARY_FRACTIONS);
+        }
+        if stationary_fractions
+            .iter()
+            .any(|fraction| !(0.0..=1.0).contains(fraction))
+        {
+            return Err("--stationary must be between 0 and 1".to_string());
+        }
+
+        Ok(Self {
+            sweep,
+            stationary_fractions,
+        })
+    }
+}
+
+//Times the update alone and with the `Changed<Position>` consumer, and counts the virtual entities
+//the consumer visits against the ones that actually moved
+pub fn changes(options: &ChangesOptions) -> Result<(), String> {
+    println!(
+        "{:<60} {:>10} {:>8} {:>8} {:>8} {:>9} {:>12} {:>12} {:>12}",
+        "case",
+        "stationary",
+        "size",
+        "moving",
+        "visited",
+        "visited/x",
+        "write ns/el",
+        "+read ns/el",
+        "read ns/el"
+    );
+
+    let sweep = &options.sweep;
+    let cases = CHANGE_CASES
+        .iter()
+        .filter(|case| sweep.selects(case.layout));
+
+    for case in cases {
+        for &size in &sweep.sizes {
+            for &stationary_fraction in &options.stationary_fractions {
+                let (moving, mut kernel) = (case.build)(size, stationary_fraction);
+
+                let (write, _) = time_kernel(&mut kernel, false, sweep);
+                let (write_read, visited) = time_kernel(&mut kernel, true, sweep);
+
+                let per_element = |stats: &FrameStats| stats.median / size as f64;
+
+                println!(
+                    "{:<60} {:>10} {:>8} {:>8} {:>8} {:>9.2} {:>12.3} {:>12.3} {:>12.3}",
+                    format!("{}/update_moving", case.layout),
+                    stationary_fraction,
+                    size,
+                    moving,
+                    visited,
+                    //How many virtual entities the consumer reads per one that moved
+                    visited as f64 / moving.max(1) as f64,
+                    per_element(&write),
+                    per_element(&write_read),
+                    per_element(&write_read) - per_element(&write)
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+//Returns the frame stats and the virtual entities the consumer visited on the last frame
+fn time_kernel(
+    kernel: &mut ChangeKernel,
+    consume: bool,
+    sweep: &SweepOptions,
+) -> (FrameStats, usize) {
+    let mut visited = 0;
+    let stats = time_frames(
+        |rng| visited = kernel(rng, consume),
+        sweep.frames,
+        sweep.warmup,
+    );
+
+    (stats, visited)
+}
@@ -0,0 +1,102 @@
+use crate::impls::cold::{self, ColdCount, ColdSize};
+use crate::impls::registry;
+use crate::impls::setup::Setup;
+use crate::runner::{time_frames, SweepOptions};
+
+pub const USAGE: &str = "\
+usage: bevy-simd-bench cold [options]
+
+cold options:
+  --layout <name>       layout to run, may be repeated (default: all)
+  --size <n>            number of virtual entities, may be repeated (default: 16383 32767)
+  --count <n>           cold components per entity, one of 4 8 16, may be repeated (default: all)
+  --bytes <n>           size of each cold component, one of 4 16 64 256, may be repeated
+                        (default: all)
+  --frames <n>          timed frames per case (default: 1000)
+  --warmup <n>          untimed frames run before timing (default: 100)";
+
+pub struct ColdOptions {
+    pub sweep: SweepOptions,
+    pub counts: Vec<ColdCount>,
+    pub sizes: Vec<ColdSize>,
+}
+
+impl ColdOptions {
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let (sweep, [counts, bytes]) = SweepOptions::parse(args, ["--count", "--bytes"])?;
+
+        sweep.check_layouts(
+            |layout| registry::setup_cases().any(|case| case.layout == layout),
+            "cold",
+        )?;
+        let mut counts = counts
+            .into_iter()
+            .map(ColdCount::from_count)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut sizes = bytes
+            .into_iter()
+            .map(ColdSize::from_bytes)
+            .collect::<Result<Vec<_>, _>>()?;
+        if counts.is_empty() {
+            counts.extend(ColdCount::ALL);
+        }
+        if sizes.is_empty() {
+            sizes.extend(ColdSize::ALL);
+        }
+
+        Ok(Self {
+            sweep,
+            counts,
+            sizes,
+        })
+    }
+}
+
+pub fn cold(options: &ColdOptions) -> Result<(), String> {
+    println!(
+        "{:<60} {:>6} {:>6} {:>8} {:>12} {:>12} {:>12} {:>10}",
+        "case", "count", "bytes", "size", "min ns", "median ns", "mean ns", "ns/elem"
+    );
+
+    let sweep = &options.sweep;
+    let cases = registry::setup_cases().filter(|case| sweep.selects(case.layout));
+
+    for case in cases {
+        let build = case.build_with_setup.unwrap();
+
+        for &size in &sweep.sizes {
+            //The plain layout first, as the baseline the cold rows are read against
+            let runs = [None]
+                .into_iter()
+                .chain(options.sizes.iter().flat_map(|&bytes| {
+                    options
+                        .counts
+                        .iter()
+                        .map(move |&count| Some((count, bytes)))
+                }));
+
+            for run in runs {
+                let add_cold = |world| match run {
+                    None => world,
+                    Some((count, bytes)) => cold::add_cold(world, count, bytes),
+                };
+                let kernel = build(size, &Setup::with_transform(&add_cold));
+                let stats = time_frames(kernel, sweep.frames, sweep.warmup);
+
+                println!(
+                    "{:<60} {:>6} {:>6} {:>8} {:>12.0} {:>12.0} {:>12.0} {:>10.3}",
+                    case.name(),
+                    run.map_or(0, |(count, _)| count.count()),
+                    run.map_or(0, |(_, bytes)| bytes.bytes()),
+                    size,
+                    stats.min,
+                    stats.median,
+                    stats.mean,
+                    stats.mean / size as f64
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
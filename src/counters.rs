@@ -0,0 +1,256 @@
+//Hardware performance counters through Linux perf_event_open, read around the runner's timed frames.
+//Any counter the kernel refuses (no PMU in a VM, perf_event_paranoid, non-Linux) is reported as
+//unavailable, and the software counters are always attempted, in user space only if need be, so
+//there's something to fall back on.
+
+#[derive(Debug, Copy, Clone)]
+pub enum Event {
+    Instructions,
+    Cycles,
+    L1dReadMisses,
+    LlcReadMisses,
+    BranchMisses,
+    //Software counters, available even without a PMU
+    TaskClockNs,
+    PageFaults,
+    ContextSwitches,
+}
+
+pub const EVENTS: [Event; 8] = [
+    Event::Instructions,
+    Event::Cycles,
+    Event::L1dReadMisses,
+    Event::LlcReadMisses,
+    Event::BranchMisses,
+    Event::TaskClockNs,
+    Event::PageFaults,
+    Event::ContextSwitches,
+];
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::Instructions => "instructions",
+            Event::Cycles => "cycles",
+            Event::L1dReadMisses => "L1d read misses",
+            Event::LlcReadMisses => "LLC read misses",
+            Event::BranchMisses => "branch misses",
+            Event::TaskClockNs => "task clock ns",
+            Event::PageFaults => "page faults",
+            Event::ContextSwitches => "context switches",
+        }
+    }
+}
+
+//A set of counters covering the calling thread
+pub struct Counters {
+    counters: Vec<(Event, Option<sys::Counter>)>,
+}
+
+impl Counters {
+    pub fn open() -> Self {
+        Self {
+            counters: EVENTS
+                .iter()
+                .map(|&event| (event, sys::Counter::open(event)))
+                .collect(),
+        }
+    }
+
+    pub fn start(&mut self) {
+        for counter in self.counters.iter_mut().filter_map(|(_, c)| c.as_mut()) {
+            counter.reset_and_enable();
+        }
+    }
+
+    //Counts since `start`, None for counters that couldn't be opened or read
+    pub fn stop(&mut self) -> Vec<(Event, Option<f64>)> {
+        self.counters
+            .iter_mut()
+            .map(|(event, counter)| (*event, counter.as_mut().and_then(|c| c.disable_and_read())))
+            .collect()
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use super::Event;
+
+    const PERF_TYPE_HARDWARE: u32 = 0;
+    const PERF_TYPE_SOFTWARE: u32 = 1;
+    const PERF_TYPE_HW_CACHE: u32 = 3;
+
+    const PERF_COUNT_HW_CPU_CYCLES: u64 = 0;
+    const PERF_COUNT_HW_INSTRUCTIONS: u64 = 1;
+    const PERF_COUNT_HW_BRANCH_MISSES: u64 = 5;
+
+    const PERF_COUNT_HW_CACHE_L1D: u64 = 0;
+    const PERF_COUNT_HW_CACHE_LL: u64 = 2;
+    const PERF_COUNT_HW_CACHE_OP_READ: u64 = 0;
+    const PERF_COUNT_HW_CACHE_RESULT_MISS: u64 = 1;
+
+    const PERF_COUNT_SW_TASK_CLOCK: u64 = 1;
+    const PERF_COUNT_SW_PAGE_FAULTS: u64 = 2;
+    const PERF_COUNT_SW_CONTEXT_SWITCHES: u64 = 3;
+
+    const PERF_FORMAT_TOTAL_TIME_ENABLED: u64 = 1 << 0;
+    const PERF_FORMAT_TOTAL_TIME_RUNNING: u64 = 1 << 1;
+
+    const FLAG_DISABLED: u64 = 1 << 0;
+    const FLAG_EXCLUDE_KERNEL: u64 = 1 << 5;
+    const FLAG_EXCLUDE_HV: u64 = 1 << 6;
+
+    const PERF_EVENT_IOC_ENABLE: libc::c_ulong = 0x2400;
+    const PERF_EVENT_IOC_DISABLE: libc::c_ulong = 0x2401;
+    const PERF_EVENT_IOC_RESET: libc::c_ulong = 0x2403;
+
+    //struct perf_event_attr, PERF_ATTR_SIZE_VER5.  Only ever handed to the kernel.
+    #[allow(dead_code)]
+    #[repr(C)]
+    #[derive(Default)]
+    struct PerfEventAttr {
+        type_: u32,
+        size: u32,
+        config: u64,
+        sample_period: u64,
+        sample_type: u64,
+        read_format: u64,
+        flags: u64,
+        wakeup_events: u32,
+        bp_type: u32,
+        config1: u64,
+        config2: u64,
+        branch_sample_type: u64,
+        sample_regs_user: u64,
+        sample_stack_user: u32,
+        clockid: i32,
+        sample_regs_intr: u64,
+        aux_watermark: u32,
+        sample_max_stack: u16,
+        reserved_2: u16,
+    }
+
+    pub struct Counter {
+        fd: libc::c_int,
+    }
+
+    impl Counter {
+        pub fn open(event: Event) -> Option<Self> {
+            let cache_miss = |cache: u64| {
+                cache | (PERF_COUNT_HW_CACHE_OP_READ << 8) | (PERF_COUNT_HW_CACHE_RESULT_MISS << 16)
+            };
+
+            let (type_, config) = match event {
+                Event::Instructions => (PERF_TYPE_HARDWARE, PERF_COUNT_HW_INSTRUCTIONS),
+                Event::Cycles => (PERF_TYPE_HARDWARE, PERF_COUNT_HW_CPU_CYCLES),
+                Event::L1dReadMisses => (PERF_TYPE_HW_CACHE, cache_miss(PERF_COUNT_HW_CACHE_L1D)),
+                Event::LlcReadMisses => (PERF_TYPE_HW_CACHE, cache_miss(PERF_COUNT_HW_CACHE_LL)),
+                Event::BranchMisses => (PERF_TYPE_HARDWARE, PERF_COUNT_HW_BRANCH_MISSES),
+                Event::TaskClockNs => (PERF_TYPE_SOFTWARE, PERF_COUNT_SW_TASK_CLOCK),
+                Event::PageFaults => (PERF_TYPE_SOFTWARE, PERF_COUNT_SW_PAGE_FAULTS),
+                Event::ContextSwitches => (PERF_TYPE_SOFTWARE, PERF_COUNT_SW_CONTEXT_SWITCHES),
+            };
+
+            let attr = |exclude| PerfEventAttr {
+                type_,
+                size: std::mem::size_of::<PerfEventAttr>() as u32,
+                config,
+                read_format: PERF_FORMAT_TOTAL_TIME_ENABLED | PERF_FORMAT_TOTAL_TIME_RUNNING,
+                flags: FLAG_DISABLED | exclude,
+                ..Default::default()
+            };
+            let user_only = FLAG_EXCLUDE_KERNEL | FLAG_EXCLUDE_HV;
+
+            //Hardware events count user space only, so they also open with perf_event_paranoid = 2.
+            //Software events try to include the kernel first, where context switches happen.  If
+            //that's not allowed they retry user space only, which still counts the task clock and
+            //the page faults user code takes, though context switches then read 0.
+            let fd = if type_ == PERF_TYPE_SOFTWARE {
+                perf_event_open(&attr(0)).or_else(|error| match error.raw_os_error() {
+                    Some(libc::EACCES) | Some(libc::EPERM) => perf_event_open(&attr(user_only)),
+                    _ => Err(error),
+                })
+            } else {
+                perf_event_open(&attr(user_only))
+            };
+
+            fd.ok().map(|fd| Self { fd })
+        }
+
+        pub fn reset_and_enable(&mut self) {
+            unsafe {
+                libc::ioctl(self.fd, PERF_EVENT_IOC_RESET, 0);
+                libc::ioctl(self.fd, PERF_EVENT_IOC_ENABLE, 0);
+            }
+        }
+
+        pub fn disable_and_read(&mut self) -> Option<f64> {
+            //value, time enabled, time running
+            let mut values = [0u64; 3];
+
+            let read = unsafe {
+                libc::ioctl(self.fd, PERF_EVENT_IOC_DISABLE, 0);
+                libc::read(
+                    self.fd,
+                    values.as_mut_ptr() as *mut libc::c_void,
+                    std::mem::size_of_val(&values),
+                )
+            };
+
+            let [value, enabled, running] = values;
+            if read != std::mem::size_of_val(&values) as isize || running == 0 {
+                return None;
+            }
+
+            //Scale up if the PMU had to multiplex this counter with others
+            Some(value as f64 * enabled as f64 / running as f64)
+        }
+    }
+
+    //For this thread, on any CPU, in no group
+    fn perf_event_open(attr: &PerfEventAttr) -> std::io::Result<libc::c_int> {
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_perf_event_open,
+                attr as *const PerfEventAttr,
+                0,
+                -1,
+                -1,
+                0,
+            )
+        };
+
+        if fd < 0 {
+            Err(std::io::Error::last_os_error())
+        } else {
+            Ok(fd as libc::c_int)
+        }
+    }
+
+    impl Drop for Counter {
+        fn drop(&mut self) {
+            unsafe {
+                libc::close(self.fd);
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+    use super::Event;
+
+    pub struct Counter;
+
+    impl Counter {
+        pub fn open(_event: Event) -> Option<Self> {
+            None
+        }
+
+        pub fn reset_and_enable(&mut self) {}
+
+        pub fn disable_and_read(&mut self) -> Option<f64> {
+            None
+        }
+    }
+}
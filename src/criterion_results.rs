@@ -0,0 +1,310 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::impls::registry::CASES;
+
+pub const DEFAULT_CRITERION_DIR: &str = "target/criterion";
+
+//The parts of criterion's `new/benchmark.json` we care about
+#[derive(Deserialize)]
+struct BenchmarkFile {
+    group_id: String,
+    function_id: Option<String>,
+    value_str: Option<String>,
+    throughput: Option<Throughput>,
+}
+
+//Bytes is reported by the bandwidth benchmarks, Elements by everything else
+#[derive(Deserialize)]
+enum Throughput {
+    Bytes(u64),
+    Elements(u64),
+    BytesDecimal(u64),
+}
+
+//The parts of criterion's `new/estimates.json` we care about.  All times are in nanoseconds.
+#[derive(Deserialize)]
+struct EstimatesFile {
+    mean: Estimate,
+}
+
+#[derive(Deserialize)]
+struct Estimate {
+    confidence_interval: ConfidenceInterval,
+    point_estimate: f64,
+}
+
+#[derive(Deserialize)]
+struct ConfidenceInterval {
+    confidence_level: f64,
+    lower_bound: f64,
+    upper_bound: f64,
+}
+
+//The latest criterion measurement of one benchmark
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Measurement {
+    pub group: String,
+    pub benchmark: String,
+    pub layout: String,
+    pub workload: String,
+    pub size: u64,
+    pub mean_ns: f64,
+    pub mean_lower_ns: f64,
+    pub mean_upper_ns: f64,
+    pub confidence_level: f64,
+    //Bytes moved per iteration, for benchmarks reporting byte throughput
+    pub bytes: Option<u64>,
+}
+
+impl Measurement {
+    pub fn elements_per_second(&self) -> f64 {
+        self.size as f64 / self.mean_ns * 1e9
+    }
+
+    pub fn ns_per_element(&self) -> f64 {
+        self.mean_ns / self.size as f64
+    }
+
+    pub fn bytes_per_element(&self) -> Option<f64> {
+        self.bytes.map(|bytes| bytes as f64 / self.size as f64)
+    }
+
+    //bytes per ns is GB/s
+    pub fn gigabytes_per_second(&self) -> Option<f64> {
+        self.bytes.map(|bytes| bytes as f64 / self.mean_ns)
+    }
+}
+
+//Loads every benchmark under `dir` that has a `new` measurement, sorted by group, layout, workload and size
+pub fn load(dir: &Path) -> Result<Vec<Measurement>, String> {
+    let mut files = Vec::new();
+    find_benchmark_files(dir, &mut files)
+        .map_err(|e| format!("couldn't read {}: {}", dir.display(), e))?;
+
+    if files.is_empty() {
+        return Err(format!(
+            "no criterion results under {}, run `cargo bench` first",
+            dir.display()
+        ));
+    }
+
+    let mut measurements = files
+        .iter()
+        .map(|file| load_measurement(file))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    measurements.sort_by(|a, b| {
+        (&a.group, &a.layout, &a.workload, a.size).cmp(&(&b.group, &b.layout, &b.workload, b.size))
+    });
+
+    Ok(measurements)
+}
+
+fn find_benchmark_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_dir() {
+            continue;
+        }
+
+        //criterion keeps the previous run in `base` and the html report in `report`
+        if path.file_name().map_or(false, |name| name == "new") {
+            let file = path.join("benchmark.json");
+            if file.exists() {
+                files.push(file);
+            }
+        } else {
+            find_benchmark_files(&path, files)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn load_measurement(benchmark_file: &Path) -> Result<Measurement, String> {
+    let benchmark: BenchmarkFile = read_json(benchmark_file)?;
+    let estimates: EstimatesFile = read_json(&benchmark_file.with_file_name("estimates.json"))?;
+
+    let benchmark_id = benchmark.function_id.unwrap_or_default();
+
+    //Every benchmark in this crate is parameterized by the number of virtual entities
+    let size = match (&benchmark.throughput, &benchmark.value_str) {
+        (Some(Throughput::Elements(elements)), _) => *elements,
+        (_, Some(value)) => value.parse().map_err(|_| {
+            format!(
+                "{}: parameter {} isn't a size",
+                benchmark_file.display(),
+                value
+            )
+        })?,
+        _ => {
+            return Err(format!(
+                "{}: benchmark has no size",
+                benchmark_file.display()
+            ))
+        }
+    };
+
+    let bytes = match benchmark.throughput {
+        Some(Throughput::Bytes(bytes)) | Some(Throughput::BytesDecimal(bytes)) => Some(bytes),
+        _ => None,
+    };
+
+    let (layout, workload) = split_benchmark_id(&benchmark_id);
+    let mean = estimates.mean;
+
+    Ok(Measurement {
+        group: benchmark.group_id,
+        benchmark: benchmark_id,
+        layout,
+        workload,
+        size,
+        mean_ns: mean.point_estimate,
+        mean_lower_ns: mean.confidence_interval.lower_bound,
+        mean_upper_ns: mean.confidence_interval.upper_bound,
+        confidence_level: mean.confidence_interval.confidence_level,
+        bytes,
+    })
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let contents =
+        fs::read_to_string(path).map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+    serde_json::from_str(&contents).map_err(|e| format!("couldn't parse {}: {}", path.display(), e))
+}
+
+//Bench ids are `<layout>` or `<layout>_<workload>`.  The layout is the longest known layout name
+//that prefixes the id; anything that isn't a known layout is reported as its own layout.
+fn split_benchmark_id(id: &str) -> (String, String) {
+    let layout = CASES
+        .iter()
+        .map(|case| case.layout)
+        .filter(|layout| {
+            id.strip_prefix(layout)
+                .map_or(false, |rest| rest.is_empty() || rest.starts_with('_'))
+        })
+        .max_by_key(|layout| layout.len());
+
+    match layout {
+        Some(layout) => {
+            let suffix = id[layout.len()..].strip_prefix('_').unwrap_or_default();
+            (layout.to_string(), registry_workload(layout, suffix))
+        }
+        None => (id.to_string(), "update".to_string()),
+    }
+}
+
+//The registry's name for the workload a bench id ends with.  The simd_benchmarks group leaves out the
+//workload's kind, so `vec3_nochangedetect` is vec3's explosion_nochangedetect and a bare
+//`simd_batch_4_soa_simulated` is its update.  The kind is the first word of the layout's workloads.
+fn registry_workload(layout: &str, suffix: &str) -> String {
+    let mut workloads = CASES
+        .iter()
+        .filter(|case| case.layout == layout)
+        .map(|case| case.workload);
+
+    if workloads.clone().any(|workload| workload == suffix) {
+        return suffix.to_string();
+    }
+
+    let kind = workloads
+        .next()
+        .and_then(|workload| workload.split('_').next())
+        .unwrap_or("update");
+
+    if suffix.is_empty() {
+        kind.to_string()
+    } else if suffix.starts_with(kind) {
+        suffix.to_string()
+    } else {
+        format!("{}_{}", kind, suffix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_split(id: &str, layout: &str, workload: &str) {
+        assert_eq!(
+            split_benchmark_id(id),
+            (layout.to_string(), workload.to_string())
+        );
+    }
+
+    #[test]
+    fn workloads_match_the_registry() {
+        assert_split("aosoa_sse4", "aosoa_sse4", "update");
+        assert_split("vec3_nochangedetect", "vec3", "explosion_nochangedetect");
+        assert_split(
+            "simd_batch_4_soa_simulated_nochangedetect",
+            "simd_batch_4_soa_simulated",
+            "update_nochangedetect",
+        );
+        assert_split(
+            "aosoa_sse4_oscillator_glam",
+            "aosoa_sse4",
+            "oscillator_glam",
+        );
+    }
+
+    #[test]
+    fn longest_layout_wins() {
+        assert_split(
+            "aosoa_sse4_f16_nochangedetect",
+            "aosoa_sse4_f16",
+            "update_nochangedetect",
+        );
+        assert_split(
+            "simd_batch_4_soa_simulated_portable_update",
+            "simd_batch_4_soa_simulated_portable",
+            "update",
+        );
+    }
+
+    #[test]
+    fn unknown_ids_are_their_own_layout() {
+        assert_split("copy", "copy", "update");
+    }
+
+    #[test]
+    fn loads_a_criterion_benchmark() {
+        let dir = std::env::temp_dir().join(format!("simd-bench-results-{}", std::process::id()));
+        let new = dir
+            .join("simd_benchmarks")
+            .join("aosoa_sse4")
+            .join("16383")
+            .join("new");
+        fs::create_dir_all(&new).unwrap();
+        fs::write(
+            new.join("benchmark.json"),
+            r#"{"group_id":"simd_benchmarks","function_id":"aosoa_sse4","value_str":"16383","throughput":{"Elements":16383}}"#,
+        )
+        .unwrap();
+        fs::write(
+            new.join("estimates.json"),
+            r#"{"mean":{"confidence_interval":{"confidence_level":0.95,"lower_bound":9.0,"upper_bound":11.0},"point_estimate":10.0}}"#,
+        )
+        .unwrap();
+
+        let measurements = load(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        let measurements = measurements.unwrap();
+
+        assert_eq!(measurements.len(), 1);
+        let m = &measurements[0];
+        assert_eq!(
+            (m.group.as_str(), m.layout.as_str(), m.workload.as_str()),
+            ("simd_benchmarks", "aosoa_sse4", "update")
+        );
+        assert_eq!(m.size, 16383);
+        assert_eq!(
+            (m.mean_ns, m.mean_lower_ns, m.mean_upper_ns),
+            (10.0, 9.0, 11.0)
+        );
+        assert_eq!(m.bytes, None);
+    }
+}
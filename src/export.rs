@@ -0,0 +1,215 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::calibrate::{self, Calibration};
+use crate::criterion_results::{self, Measurement};
+use crate::impls::registry;
+
+pub const USAGE: &str = "\
+usage: bevy-simd-bench export [options]
+
+export options:
+  --criterion-dir <dir>   criterion output to read (default: target/criterion)
+  --json <file>           JSON output (default: target/simd-bench/results.json)
+  --csv <file>            CSV output (default: target/simd-bench/results.csv)
+  --calibration <file>    roofline calibration from `calibrate`, used if present
+                          (default: target/simd-bench/calibration.json)";
+
+const DEFAULT_JSON: &str = "target/simd-bench/results.json";
+const DEFAULT_CSV: &str = "target/simd-bench/results.csv";
+
+const CSV_HEADER: &str =
+    "group,benchmark,layout,workload,size,mean_ns,mean_lower_ns,mean_upper_ns,\
+confidence_level,elements_per_second,ns_per_element,bytes_per_element,gigabytes_per_second,\
+flops_per_element,arithmetic_intensity,gigaflops,attainable_gigaflops,roofline_fraction";
+
+//One row of the layout × size matrix
+#[derive(Serialize)]
+pub struct Row {
+    pub group: String,
+    pub benchmark: String,
+    pub layout: String,
+    pub workload: String,
+    pub size: u64,
+    pub mean_ns: f64,
+    pub mean_lower_ns: f64,
+    pub mean_upper_ns: f64,
+    pub confidence_level: f64,
+    pub elements_per_second: f64,
+    pub ns_per_element: f64,
+    pub bytes_per_element: Option<f64>,
+    pub gigabytes_per_second: Option<f64>,
+    //Roofline placement, for workloads with known FLOP and byte counts
+    pub flops_per_element: Option<f64>,
+    pub arithmetic_intensity: Option<f64>,
+    pub gigaflops: Option<f64>,
+    pub attainable_gigaflops: Option<f64>,
+    pub roofline_fraction: Option<f64>,
+}
+
+impl Row {
+    fn new(m: &Measurement, calibration: Option<&Calibration>) -> Self {
+        let flops_per_element = registry::flops_per_element(&m.workload);
+        let arithmetic_intensity = flops_per_element
+            .zip(m.bytes_per_element())
+            .map(|(flops, bytes)| flops / bytes);
+        let gigaflops = flops_per_element.map(|flops| flops * m.size as f64 / m.mean_ns);
+        let attainable_gigaflops = arithmetic_intensity
+            .zip(calibration)
+            .map(|(intensity, calibration)| calibration.roofline(intensity));
+
+        Self {
+            group: m.group.clone(),
+            benchmark: m.benchmark.clone(),
+            layout: m.layout.clone(),
+            workload: m.workload.clone(),
+            size: m.size,
+            mean_ns: m.mean_ns,
+            mean_lower_ns: m.mean_lower_ns,
+            mean_upper_ns: m.mean_upper_ns,
+            confidence_level: m.confidence_level,
+            elements_per_second: m.elements_per_second(),
+            ns_per_element: m.ns_per_element(),
+            bytes_per_element: m.bytes_per_element(),
+            gigabytes_per_second: m.gigabytes_per_second(),
+            flops_per_element,
+            arithmetic_intensity,
+            gigaflops,
+            attainable_gigaflops,
+            roofline_fraction: gigaflops
+                .zip(attainable_gigaflops)
+                .map(|(achieved, attainable)| achieved / attainable),
+        }
+    }
+
+    fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            csv_field(&self.group),
+            csv_field(&self.benchmark),
+            csv_field(&self.layout),
+            csv_field(&self.workload),
+            self.size,
+            self.mean_ns,
+            self.mean_lower_ns,
+            self.mean_upper_ns,
+            self.confidence_level,
+            self.elements_per_second,
+            self.ns_per_element,
+            optional(self.bytes_per_element),
+            optional(self.gigabytes_per_second),
+            optional(self.flops_per_element),
+            optional(self.arithmetic_intensity),
+            optional(self.gigaflops),
+            optional(self.attainable_gigaflops),
+            optional(self.roofline_fraction)
+        )
+    }
+}
+
+//Quotes text containing a separator, quote or line break, doubling any quotes inside (RFC 4180)
+fn csv_field(text: &str) -> String {
+    if text.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+//Missing values are left empty
+fn optional(value: Option<f64>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+pub struct ExportOptions {
+    pub criterion_dir: PathBuf,
+    pub json: PathBuf,
+    pub csv: PathBuf,
+    pub calibration: PathBuf,
+}
+
+impl ExportOptions {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            criterion_dir: criterion_results::DEFAULT_CRITERION_DIR.into(),
+            json: DEFAULT_JSON.into(),
+            csv: DEFAULT_CSV.into(),
+            calibration: calibrate::DEFAULT_CALIBRATION.into(),
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {}", arg))
+            };
+
+            match arg.as_str() {
+                "--criterion-dir" => options.criterion_dir = value()?.into(),
+                "--json" => options.json = value()?.into(),
+                "--csv" => options.csv = value()?.into(),
+                "--calibration" => options.calibration = value()?.into(),
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+pub fn export(options: &ExportOptions) -> Result<(), String> {
+    let calibration = if options.calibration.exists() {
+        Some(Calibration::load(&options.calibration)?)
+    } else {
+        println!(
+            "no calibration at {}, run `calibrate` to place results on the roofline",
+            options.calibration.display()
+        );
+        None
+    };
+
+    let rows: Vec<Row> = criterion_results::load(&options.criterion_dir)?
+        .iter()
+        .map(|m| Row::new(m, calibration.as_ref()))
+        .collect();
+
+    write_json(&options.json, &rows)?;
+    write_csv(&options.csv, &rows)?;
+
+    println!(
+        "exported {} results to {} and {}",
+        rows.len(),
+        options.json.display(),
+        options.csv.display()
+    );
+
+    Ok(())
+}
+
+pub fn create(path: &Path) -> Result<fs::File, String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("couldn't create {}: {}", parent.display(), e))?;
+    }
+    fs::File::create(path).map_err(|e| format!("couldn't create {}: {}", path.display(), e))
+}
+
+fn write_json(path: &Path, rows: &[Row]) -> Result<(), String> {
+    let file = create(path)?;
+    serde_json::to_writer_pretty(file, rows)
+        .map_err(|e| format!("couldn't write {}: {}", path.display(), e))
+}
+
+fn write_csv(path: &Path, rows: &[Row]) -> Result<(), String> {
+    let mut file = create(path)?;
+    let write_error = |e: std::io::Error| format!("couldn't write {}: {}", path.display(), e);
+
+    writeln!(file, "{}", CSV_HEADER).map_err(write_error)?;
+    for row in rows {
+        writeln!(file, "{}", row.to_csv()).map_err(write_error)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use std::fs;
+use std::process::Command;
+
+//CPU flags that matter for these kernels
+const INTERESTING_FLAGS: [&str; 7] = ["sse4_1", "sse4_2", "avx", "avx2", "fma", "f16c", "avx512f"];
+
+//Identifies the machine a set of results came from
+#[derive(Debug, Clone)]
+pub struct Fingerprint {
+    pub cpu: String,
+    pub logical_cores: usize,
+    pub cpu_flags: Vec<String>,
+    pub memory: String,
+    pub os: String,
+    pub kernel: String,
+    pub rustc: String,
+}
+
+impl Fingerprint {
+    pub fn collect() -> Self {
+        let cpuinfo = fs::read_to_string("/proc/cpuinfo").unwrap_or_default();
+
+        let cpu = cpuinfo_field(&cpuinfo, "model name").unwrap_or_else(|| "unknown".to_string());
+        let cpu_flags = cpuinfo_field(&cpuinfo, "flags")
+            .map(|flags| {
+                flags
+                    .split_whitespace()
+                    .filter(|flag| INTERESTING_FLAGS.contains(flag))
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let memory = fs::read_to_string("/proc/meminfo")
+            .ok()
+            .and_then(|meminfo| {
+                meminfo
+                    .lines()
+                    .find(|line| line.starts_with("MemTotal:"))
+                    .and_then(|line| line.split_whitespace().nth(1)?.parse::<u64>().ok())
+            })
+            .map(|kib| format!("{:.1} GiB", kib as f64 / (1024.0 * 1024.0)))
+            .unwrap_or_else(|| "unknown".to_string());
+
+        let kernel = fs::read_to_string("/proc/sys/kernel/osrelease")
+            .map(|release| release.trim().to_string())
+            .unwrap_or_else(|_| "unknown".to_string());
+
+        let rustc = Command::new("rustc")
+            .arg("--version")
+            .output()
+            .ok()
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .map(|version| version.trim().to_string())
+            .filter(|version| !version.is_empty())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        Self {
+            cpu,
+            logical_cores: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            cpu_flags,
+            memory,
+            os: format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+            kernel,
+            rustc,
+        }
+    }
+}
+
+impl std::fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "* CPU: {} ({} logical cores)",
+            self.cpu, self.logical_cores
+        )?;
+        writeln!(f, "* CPU flags: {}", self.cpu_flags.join(" "))?;
+        writeln!(f, "* Memory: {}", self.memory)?;
+        writeln!(f, "* OS: {} (kernel {})", self.os, self.kernel)?;
+        write!(f, "* Compiler: {}", self.rustc)
+    }
+}
+
+fn cpuinfo_field(cpuinfo: &str, field: &str) -> Option<String> {
+    cpuinfo
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim() == field)
+        .map(|(_, value)| value.trim().to_string())
+}
@@ -0,0 +1,116 @@
+use crate::impls::registry::FOOTPRINTS;
+use crate::runner::{parse_number, DEFAULT_SIZES};
+
+pub const USAGE: &str = "\
+usage: bevy-simd-bench footprint [options]
+
+footprint options:
+  --layout <name>     layout to measure, may be repeated (default: all)
+  --size <n>          number of virtual entities, may be repeated (default: 16383 32767)
+  --columns           also list every table column and sparse set";
+
+pub struct FootprintOptions {
+    pub layouts: Vec<String>,
+    pub sizes: Vec<i32>,
+    pub columns: bool,
+}
+
+impl FootprintOptions {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            layouts: Vec::new(),
+            sizes: Vec::new(),
+            columns: false,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {}", arg))
+            };
+
+            match arg.as_str() {
+                "--layout" => options.layouts.push(value()?),
+                "--size" => options.sizes.push(parse_number(&value()?)?),
+                "--columns" => options.columns = true,
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+
+        for layout in &options.layouts {
+            if !FOOTPRINTS.iter().any(|(known, _)| known == layout) {
+                return Err(format!("no footprint for layout {}", layout));
+            }
+        }
+        if options.sizes.is_empty() {
+            options.sizes.extend(DEFAULT_SIZES);
+        }
+
+        Ok(options)
+    }
+}
+
+//Footprint of every layout at every size, one row each so it can be joined with throughput by layout
+pub fn footprint(options: &FootprintOptions) -> Result<(), String> {
+    println!(
+        "{:<40} {:>8} {:>10} {:>6} {:>7} {:>12} {:>12} {:>9} {:>12} {:>10}",
+        "layout",
+        "size",
+        "archetypes",
+        "tables",
+        "columns",
+        "component B",
+        "padding B",
+        "padding %",
+        "ticks B",
+        "B/elem"
+    );
+
+    for &size in &options.sizes {
+        for (layout, measure) in FOOTPRINTS.iter().filter(|(layout, _)| {
+            options.layouts.is_empty() || options.layouts.iter().any(|l| l == layout)
+        }) {
+            let footprint = measure(size);
+
+            println!(
+                "{:<40} {:>8} {:>10} {:>6} {:>7} {:>12} {:>12} {:>9} {:>12} {:>10}",
+                layout,
+                footprint.virtual_entities,
+                footprint.archetypes,
+                footprint.tables,
+                footprint.columns.len(),
+                footprint.component_bytes(),
+                footprint.padding_bytes(),
+                optional(
+                    footprint
+                        .padding_fraction()
+                        .map(|fraction| 100.0 * fraction),
+                    1
+                ),
+                footprint.tick_bytes(),
+                optional(footprint.bytes_per_virtual_entity(), 2)
+            );
+
+            if options.columns {
+                for column in &footprint.columns {
+                    println!(
+                        "    {:<80} {:?} size {} align {} rows {} bytes {}",
+                        column.component,
+                        column.storage,
+                        column.size,
+                        column.align,
+                        column.rows,
+                        column.bytes()
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+//Values that can't be computed, like bytes per element of an empty world, are printed as `-`
+fn optional(value: Option<f64>, precision: usize) -> String {
+    value.map_or("-".to_string(), |value| format!("{:.*}", precision, value))
+}
@@ -0,0 +1,80 @@
+use crate::impls::fragmentation::{self, ARCHETYPE_COUNTS, MAX_ARCHETYPES};
+use crate::impls::registry;
+use crate::impls::setup::Setup;
+use crate::runner::{time_frames, SweepOptions};
+
+pub const USAGE: &str = "\
+usage: bevy-simd-bench fragmentation [options]
+
+fragmentation options:
+  --layout <name>       layout to run, may be repeated (default: all)
+  --size <n>            number of virtual entities, may be repeated (default: 16383 32767)
+  --archetypes <n>      archetypes the entities are spread over, may be repeated
+                        (default: 1 2 8 32 128 512, at most 1024)
+  --frames <n>          timed frames per case (default: 1000)
+  --warmup <n>          untimed frames run before timing (default: 100)";
+
+pub struct FragmentationOptions {
+    pub sweep: SweepOptions,
+    pub archetypes: Vec<usize>,
+}
+
+impl FragmentationOptions {
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let (sweep, [mut archetypes]) = SweepOptions::parse(args, ["--archetypes"])?;
+
+        sweep.check_layouts(
+            |layout| registry::setup_cases().any(|case| case.layout == layout),
+            "fragmented",
+        )?;
+        if archetypes.is_empty() {
+            archetypes.extend(ARCHETYPE_COUNTS);
+        }
+        if archetypes
+            .iter()
+            .any(|&archetypes| archetypes == 0 || archetypes > MAX_ARCHETYPES)
+        {
+            return Err(format!(
+                "--archetypes must be between 1 and {}",
+                MAX_ARCHETYPES
+            ));
+        }
+
+        Ok(Self { sweep, archetypes })
+    }
+}
+
+pub fn fragmentation(options: &FragmentationOptions) -> Result<(), String> {
+    println!(
+        "{:<60} {:>10} {:>8} {:>12} {:>12} {:>12} {:>10}",
+        "case", "archetypes", "size", "min ns", "median ns", "mean ns", "ns/elem"
+    );
+
+    let sweep = &options.sweep;
+    let cases = registry::setup_cases().filter(|case| sweep.selects(case.layout));
+
+    for case in cases {
+        let build = case.build_with_setup.unwrap();
+
+        for &size in &sweep.sizes {
+            for &archetypes in &options.archetypes {
+                let fragment = |world| fragmentation::fragment(world, archetypes);
+                let kernel = build(size, &Setup::with_transform(&fragment));
+                let stats = time_frames(kernel, sweep.frames, sweep.warmup);
+
+                println!(
+                    "{:<60} {:>10} {:>8} {:>12.0} {:>12.0} {:>12.0} {:>10.3}",
+                    case.name(),
+                    archetypes,
+                    size,
+                    stats.min,
+                    stats.median,
+                    stats.mean,
+                    stats.mean / size as f64
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use std::time::Instant;
+
+use crate::impls::lifecycle::Operation;
+use crate::impls::registry::{LifecycleCase, LIFECYCLE_CASES};
+use crate::runner::{parse_number, DEFAULT_SIZES};
+use crate::stats::FrameStats;
+
+//Every run builds a fresh world, so far fewer of them than frames of an update
+const DEFAULT_RUNS: usize = 20;
+const DEFAULT_WARMUP_RUNS: usize = 2;
+
+pub const USAGE: &str = "\
+usage: bevy-simd-bench lifecycle [options]
+
+lifecycle options:
+  --layout <name>       layout to run, may be repeated (default: all)
+  --operation <name>    spawn_batch, spawn, commands, insert or remove, may be repeated
+                        (default: all)
+  --size <n>            number of virtual entities, may be repeated (default: 16383 32767)
+  --runs <n>            timed runs per case, each on a freshly built world (default: 20)
+  --warmup <n>          untimed runs before timing (default: 2)";
+
+pub struct LifecycleOptions {
+    pub layouts: Vec<String>,
+    pub operations: Vec<Operation>,
+    pub sizes: Vec<i32>,
+    pub runs: usize,
+    pub warmup: usize,
+}
+
+impl LifecycleOptions {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            layouts: Vec::new(),
+            operations: Vec::new(),
+            sizes: Vec::new(),
+            runs: DEFAULT_RUNS,
+            warmup: DEFAULT_WARMUP_RUNS,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {}", arg))
+            };
+
+            match arg.as_str() {
+                "--layout" => options.layouts.push(value()?),
+                "--operation" => {
+                    let name = value()?;
+                    let operation = Operation::ALL
+                        .into_iter()
+                        .find(|operation| operation.name() == name)
+                        .ok_or_else(|| format!("unknown operation {}", name))?;
+                    options.operations.push(operation);
+                }
+                "--size" => options.sizes.push(parse_number(&value()?)?),
+                "--runs" => options.runs = parse_number(&value()?)?,
+                "--warmup" => options.warmup = parse_number(&value()?)?,
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+
+        for layout in &options.layouts {
+            if !LIFECYCLE_CASES
+                .iter()
+                .any(|case| case.layout == layout.as_str())
+            {
+                return Err(format!("no lifecycle benchmark for layout {}", layout));
+            }
+        }
+        if options.operations.is_empty() {
+            options.operations.extend(Operation::ALL);
+        }
+        if options.sizes.is_empty() {
+            options.sizes.extend(DEFAULT_SIZES);
+        }
+        if options.runs == 0 {
+            return Err("--runs must be at least 1".to_string());
+        }
+
+        Ok(options)
+    }
+}
+
+//Times each operation on each layout, per virtual entity so layouts packing different numbers of
+//them into an entity compare directly
+pub fn lifecycle(options: &LifecycleOptions) -> Result<(), String> {
+    println!(
+        "{:<60} {:>8} {:>12} {:>12} {:>12}",
+        "case", "size", "min ns/el", "median ns/el", "p95 ns/el"
+    );
+
+    let cases = LIFECYCLE_CASES.iter().filter(|case| {
+        options.layouts.is_empty() || options.layouts.iter().any(|l| l == case.layout)
+    });
+
+    for case in cases {
+        for &operation in &options.operations {
+            for &size in &options.sizes {
+                let stats = time_operation(case, operation, size, options.runs, options.warmup);
+                let per_element = |ns: f64| ns / size as f64;
+
+                println!(
+                    "{:<60} {:>8} {:>12.3} {:>12.3} {:>12.3}",
+                    format!("{}/{}", case.layout, operation.name()),
+                    size,
+                    per_element(stats.min),
+                    per_element(stats.median),
+                    per_element(stats.p95)
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+//Builds every run's world first and drops it after, so only the operation itself is timed
+fn time_operation(
+    case: &LifecycleCase,
+    operation: Operation,
+    size: i32,
+    runs: usize,
+    warmup: usize,
+) -> FrameStats {
+    for _ in 0..warmup {
+        (case.prepare)(size, operation)();
+    }
+
+    let run_times: Vec<_> = (0..runs)
+        .map(|_| {
+            let run = (case.prepare)(size, operation);
+            let start = Instant::now();
+            let world = run();
+            let elapsed = start.elapsed();
+            drop(world);
+            elapsed
+        })
+        .collect();
+
+    FrameStats::new(&run_times)
+}
@@ -1,33 +1,95 @@
-use std::marker::PhantomData;
+#![feature(portable_simd)]
+#![feature(slice_as_chunks)]
 
-use bevy::prelude::*;
+//Runs the benchmark layouts outside of criterion, for quick A/B experiments and for profiling under
+//perf/valgrind, and post-processes criterion's results.
 
-fn main() {
-    App::new().add_startup_system(setup).run();
-}
-
-struct X;
-struct Y;
-struct Z;
-
-#[derive(Component)]
-struct Position<Name>(f32, PhantomData<Name>);
+mod audit;
+mod baseline;
+mod calibrate;
+mod changes;
+mod cold;
+mod counters;
+mod criterion_results;
+mod export;
+mod fingerprint;
+mod footprint;
+mod fragmentation;
+//Layout implementations are shared with the criterion benches
+#[path = "../benches/impls/mod.rs"]
+mod impls;
+mod lifecycle;
+mod migration;
+mod parallel;
+mod quantization;
+mod report;
+mod runner;
+mod stats;
 
-#[derive(Bundle)]
-struct PositionBundle {
-    x: Position<X>,
-    y: Position<Y>,
-    z: Position<Z>,
-}
+fn main() {
+    let mut args = std::env::args().skip(1);
 
-#[derive(Component)]
-struct Velocity<Name>(f32, PhantomData<Name>);
+    let result = match args.next().as_deref() {
+        Some("run") => runner::RunOptions::parse(args).and_then(|options| runner::run(&options)),
+        Some("export") => {
+            export::ExportOptions::parse(args).and_then(|options| export::export(&options))
+        }
+        Some("report") => {
+            report::ReportOptions::parse(args).and_then(|options| report::report(&options))
+        }
+        Some("calibrate") => calibrate::CalibrateOptions::parse(args)
+            .and_then(|options| calibrate::calibrate(&options)),
+        Some("baseline") => {
+            baseline::BaselineOptions::parse(args).and_then(|options| baseline::baseline(&options))
+        }
+        Some("audit") => {
+            audit::AuditOptions::parse(args).and_then(|options| audit::audit(&options))
+        }
+        Some("footprint") => footprint::FootprintOptions::parse(args)
+            .and_then(|options| footprint::footprint(&options)),
+        Some("parallel") => {
+            parallel::ParallelOptions::parse(args).and_then(|options| parallel::parallel(&options))
+        }
+        Some("fragmentation") => fragmentation::FragmentationOptions::parse(args)
+            .and_then(|options| fragmentation::fragmentation(&options)),
+        Some("cold") => cold::ColdOptions::parse(args).and_then(|options| cold::cold(&options)),
+        Some("changes") => {
+            changes::ChangesOptions::parse(args).and_then(|options| changes::changes(&options))
+        }
+        Some("lifecycle") => lifecycle::LifecycleOptions::parse(args)
+            .and_then(|options| lifecycle::lifecycle(&options)),
+        Some("migration") => migration::MigrationOptions::parse(args)
+            .and_then(|options| migration::migration(&options)),
+        Some("quantization") => quantization::QuantizationOptions::parse(args)
+            .and_then(|options| quantization::quantization(&options)),
+        Some("list") => {
+            runner::list();
+            Ok(())
+        }
+        _ => {
+            eprintln!(
+                "{}\n\n{}\n\n{}\n\n{}\n\n{}\n\n{}\n\n{}\n\n{}\n\n{}\n\n{}\n\n{}\n\n{}\n\n{}\n\n{}",
+                runner::USAGE,
+                export::USAGE,
+                report::USAGE,
+                calibrate::USAGE,
+                baseline::USAGE,
+                audit::USAGE,
+                footprint::USAGE,
+                parallel::USAGE,
+                fragmentation::USAGE,
+                cold::USAGE,
+                changes::USAGE,
+                lifecycle::USAGE,
+                migration::USAGE,
+                quantization::USAGE
+            );
+            std::process::exit(2);
+        }
+    };
 
-#[derive(Bundle)]
-struct VelocityBundle {
-    x: Velocity<X>,
-    y: Velocity<Y>,
-    z: Velocity<Z>,
+    if let Err(error) = result {
+        eprintln!("error: {}", error);
+        std::process::exit(1);
+    }
 }
-
-fn setup(mut commands: Commands) {}
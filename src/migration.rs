@@ -0,0 +1,182 @@
+use std::time::{Duration, Instant};
+
+use crate::impls::{aosoa_sse4, vec3_aos};
+use crate::runner::{parse_number, DEFAULT_FRAMES, DEFAULT_SIZES, DEFAULT_WARMUP_FRAMES};
+use crate::stats::FrameStats;
+
+//Every conversion run needs a freshly spawned world, so far fewer of them than frames of an update
+const DEFAULT_RUNS: usize = 20;
+
+const TIME_STEP: f32 = 1.0 / 60.0;
+
+pub const USAGE: &str = "\
+usage: bevy-simd-bench migration [options]
+
+migration options:
+  --size <n>            number of virtual entities, may be repeated (default: 16383 32767)
+  --runs <n>            timed conversions each way, each on a freshly spawned world (default: 20)
+  --frames <n>          timed update frames per layout (default: 1000)
+  --warmup <n>          untimed update frames run before timing (default: 100)";
+
+pub struct MigrationOptions {
+    pub sizes: Vec<i32>,
+    pub runs: usize,
+    pub frames: usize,
+    pub warmup: usize,
+}
+
+impl MigrationOptions {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            sizes: Vec::new(),
+            runs: DEFAULT_RUNS,
+            frames: DEFAULT_FRAMES,
+            warmup: DEFAULT_WARMUP_FRAMES,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {}", arg))
+            };
+
+            match arg.as_str() {
+                "--size" => options.sizes.push(parse_number(&value()?)?),
+                "--runs" => options.runs = parse_number(&value()?)?,
+                "--frames" => options.frames = parse_number(&value()?)?,
+                "--warmup" => options.warmup = parse_number(&value()?)?,
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+
+        if options.sizes.is_empty() {
+            options.sizes.extend(DEFAULT_SIZES);
+        }
+        if options.runs == 0 {
+            return Err("--runs must be at least 1".to_string());
+        }
+        if options.frames == 0 {
+            return Err("--frames must be at least 1".to_string());
+        }
+
+        Ok(options)
+    }
+}
+
+//Times the conversion from vec3_aos to aosoa_sse4 and back against an update frame of each, and
+//prints how many frames in aosoa_sse4 it takes to earn back the round trip
+pub fn migration(options: &MigrationOptions) -> Result<(), String> {
+    println!(
+        "{:>8} {:>14} {:>14} {:>14} {:>14} {:>12}",
+        "size", "to aosoa ns/el", "to aos ns/el", "aos ns/el", "aosoa ns/el", "break-even"
+    );
+
+    for &size in &options.sizes {
+        let to_aosoa = time_runs(options.runs, || {
+            let aos = vec3_aos::Benchmark::new(size);
+            let start = Instant::now();
+            let converted = aosoa_sse4::Benchmark::from_aos(aos);
+            let elapsed = start.elapsed();
+            drop(converted);
+            elapsed
+        });
+        let to_aos = time_runs(options.runs, || {
+            let (aosoa, lanes) = aosoa_sse4::Benchmark::from_aos(vec3_aos::Benchmark::new(size));
+            let start = Instant::now();
+            let converted = aosoa.into_aos(lanes);
+            let elapsed = start.elapsed();
+            drop(converted);
+            elapsed
+        });
+
+        let mut aos = vec3_aos::Benchmark::new(size);
+        let aos_update = time_frames(options.frames, options.warmup, || aos.run(TIME_STEP));
+
+        //The converted world rather than a freshly spawned one, with its partly empty last block
+        let (mut aosoa, _) = aosoa_sse4::Benchmark::from_aos(vec3_aos::Benchmark::new(size));
+        let aosoa_update = time_frames(options.frames, options.warmup, || aosoa.run(TIME_STEP));
+
+        let per_element = |stats: &FrameStats| stats.median / size as f64;
+
+        let saved = aos_update.median - aosoa_update.median;
+        let break_even = if saved > 0.0 {
+            format!("{:.0}", (to_aosoa.median + to_aos.median) / saved)
+        } else {
+            "never".to_string()
+        };
+
+        println!(
+            "{:>8} {:>14.3} {:>14.3} {:>14.3} {:>14.3} {:>12}",
+            size,
+            per_element(&to_aosoa),
+            per_element(&to_aos),
+            per_element(&aos_update),
+            per_element(&aosoa_update),
+            break_even
+        );
+    }
+
+    Ok(())
+}
+
+fn time_runs(runs: usize, mut run: impl FnMut() -> Duration) -> FrameStats {
+    let run_times: Vec<_> = (0..runs).map(|_| run()).collect();
+    FrameStats::new(&run_times)
+}
+
+fn time_frames(frames: usize, warmup: usize, mut frame: impl FnMut()) -> FrameStats {
+    for _ in 0..warmup {
+        frame();
+    }
+
+    let frame_times: Vec<_> = (0..frames)
+        .map(|_| {
+            let start = Instant::now();
+            frame();
+            start.elapsed()
+        })
+        .collect();
+
+    FrameStats::new(&frame_times)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Not a multiple of 4, so the last block is only partly filled
+    const SIZE: i32 = 1023;
+
+    //Converts a world there and back, checking every AoS entity's position through the lane map on
+    //the way and that each entity gets its own position back at the end.  In between, the AoS
+    //entities stay alive in the packed world but empty, their values all moved into the blocks.
+    #[test]
+    fn round_trip_keeps_every_position() {
+        let mut aos = vec3_aos::Benchmark::new(SIZE);
+        let mut before = aos.positions();
+
+        let (aosoa, lanes) = aosoa_sse4::Benchmark::from_aos(aos);
+        for &(entity, position) in &before {
+            assert_eq!(
+                aosoa.position(&lanes, entity),
+                Some(position),
+                "{:?} lost its position converting to aosoa_sse4",
+                entity
+            );
+            assert_eq!(
+                aosoa.has_aos_components(entity),
+                Some(false),
+                "{:?} should be alive and empty in the aosoa_sse4 world",
+                entity
+            );
+        }
+
+        let mut after = aosoa.into_aos(lanes).positions();
+        before.sort_by_key(|&(entity, _)| entity);
+        after.sort_by_key(|&(entity, _)| entity);
+        assert_eq!(
+            before, after,
+            "positions changed converting back to vec3_aos"
+        );
+    }
+}
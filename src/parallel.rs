@@ -0,0 +1,189 @@
+use std::process::Command;
+use std::time::Instant;
+
+use crate::impls::parallel::{self, BATCH_SIZES};
+use crate::impls::registry::{ParallelCase, PARALLEL_CASES};
+use crate::runner::{parse_number, DEFAULT_FRAMES, DEFAULT_SIZES, DEFAULT_WARMUP_FRAMES};
+use crate::stats::FrameStats;
+
+pub const USAGE: &str = "\
+usage: bevy-simd-bench parallel [options]
+
+parallel options:
+  --layout <name>       layout to run, may be repeated (default: all)
+  --size <n>            number of virtual entities, may be repeated (default: 16383 32767)
+  --threads <n>         compute pool threads, may be repeated (default: one per core)
+  --batch-size <n>      entities per task, may be repeated (default: 16 64 256 1024 4096 16384)
+  --frames <n>          timed frames per case (default: 1000)
+  --warmup <n>          untimed frames run before timing (default: 100)";
+
+pub struct ParallelOptions {
+    pub layouts: Vec<String>,
+    pub sizes: Vec<i32>,
+    pub threads: Vec<usize>,
+    pub batch_sizes: Vec<usize>,
+    pub frames: usize,
+    pub warmup: usize,
+}
+
+impl ParallelOptions {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            layouts: Vec::new(),
+            sizes: Vec::new(),
+            threads: Vec::new(),
+            batch_sizes: Vec::new(),
+            frames: DEFAULT_FRAMES,
+            warmup: DEFAULT_WARMUP_FRAMES,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {}", arg))
+            };
+
+            match arg.as_str() {
+                "--layout" => options.layouts.push(value()?),
+                "--size" => options.sizes.push(parse_number(&value()?)?),
+                "--threads" => options.threads.push(parse_number(&value()?)?),
+                "--batch-size" => options.batch_sizes.push(parse_number(&value()?)?),
+                "--frames" => options.frames = parse_number(&value()?)?,
+                "--warmup" => options.warmup = parse_number(&value()?)?,
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+
+        for layout in &options.layouts {
+            if !PARALLEL_CASES
+                .iter()
+                .any(|case| case.layout == layout.as_str())
+            {
+                return Err(format!("no parallel kernel for layout {}", layout));
+            }
+        }
+        if options.sizes.is_empty() {
+            options.sizes.extend(DEFAULT_SIZES);
+        }
+        if options.batch_sizes.is_empty() {
+            options.batch_sizes.extend(BATCH_SIZES);
+        }
+        if options.threads.contains(&0) || options.batch_sizes.contains(&0) {
+            return Err("--threads and --batch-size must be at least 1".to_string());
+        }
+        if options.frames == 0 {
+            return Err("--frames must be at least 1".to_string());
+        }
+
+        Ok(options)
+    }
+
+    //The same options for a single thread count, to hand to a child process
+    fn child_args(&self, threads: usize) -> Vec<String> {
+        let mut args = vec!["parallel".to_string()];
+        for layout in &self.layouts {
+            args.extend(["--layout".to_string(), layout.clone()]);
+        }
+        for size in &self.sizes {
+            args.extend(["--size".to_string(), size.to_string()]);
+        }
+        for batch_size in &self.batch_sizes {
+            args.extend(["--batch-size".to_string(), batch_size.to_string()]);
+        }
+        args.extend([
+            "--threads".to_string(),
+            threads.to_string(),
+            "--frames".to_string(),
+            self.frames.to_string(),
+            "--warmup".to_string(),
+            self.warmup.to_string(),
+        ]);
+        args
+    }
+}
+
+pub fn parallel(options: &ParallelOptions) -> Result<(), String> {
+    //The compute pool is global and can only be built once, so each thread count gets its own process
+    if options.threads.len() > 1 {
+        let exe =
+            std::env::current_exe().map_err(|e| format!("couldn't find own executable: {}", e))?;
+        for &threads in &options.threads {
+            let status = Command::new(&exe)
+                .args(options.child_args(threads))
+                .status()
+                .map_err(|e| format!("couldn't run {}: {}", exe.display(), e))?;
+            if !status.success() {
+                return Err(format!("run with {} threads failed", threads));
+            }
+        }
+        return Ok(());
+    }
+
+    let threads = parallel::init_task_pool(options.threads.first().copied());
+
+    println!(
+        "{:<60} {:>7} {:>8} {:>11} {:>8} {:>12} {:>12} {:>12} {:>10}",
+        "case",
+        "threads",
+        "batch",
+        "batch elems",
+        "size",
+        "min ns",
+        "median ns",
+        "mean ns",
+        "ns/elem"
+    );
+
+    let cases = PARALLEL_CASES.iter().filter(|case| {
+        options.layouts.is_empty() || options.layouts.iter().any(|l| l == case.layout)
+    });
+
+    for case in cases {
+        for &size in &options.sizes {
+            for &batch_size in &options.batch_sizes {
+                let stats = run_case(case, size, batch_size, options.frames, options.warmup);
+
+                println!(
+                    "{:<60} {:>7} {:>8} {:>11} {:>8} {:>12.0} {:>12.0} {:>12.0} {:>10.3}",
+                    format!("{}/update_par", case.layout),
+                    threads,
+                    batch_size,
+                    //In virtual entities, so layouts with different lane counts compare directly
+                    batch_size * case.lanes,
+                    size,
+                    stats.min,
+                    stats.median,
+                    stats.mean,
+                    stats.mean / size as f64
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn run_case(
+    case: &ParallelCase,
+    size: i32,
+    batch_size: usize,
+    frames: usize,
+    warmup: usize,
+) -> FrameStats {
+    let mut kernel = (case.build)(size);
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..warmup {
+        kernel(&mut rng, batch_size);
+    }
+
+    let frame_times: Vec<_> = (0..frames)
+        .map(|_| {
+            let start = Instant::now();
+            kernel(&mut rng, batch_size);
+            start.elapsed()
+        })
+        .collect();
+
+    FrameStats::new(&frame_times)
+}
@@ -0,0 +1,80 @@
+use crate::impls::registry::QUANTIZATION_ERRORS;
+use crate::runner::{parse_number, DEFAULT_SIZES};
+
+//Number of updates simulated when comparing quantized layouts against the f32 reference
+const DEFAULT_ERROR_FRAMES: usize = 100;
+
+pub const USAGE: &str = "\
+usage: bevy-simd-bench quantization [options]
+
+quantization options:
+  --layout <name>     quantized layout to check, may be repeated (default: all)
+  --size <n>          number of virtual entities, may be repeated (default: 16383 32767)
+  --frames <n>        updates run before comparing against the f32 reference (default: 100)";
+
+pub struct QuantizationOptions {
+    pub layouts: Vec<String>,
+    pub sizes: Vec<i32>,
+    pub frames: usize,
+}
+
+impl QuantizationOptions {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            layouts: Vec::new(),
+            sizes: Vec::new(),
+            frames: DEFAULT_ERROR_FRAMES,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {}", arg))
+            };
+
+            match arg.as_str() {
+                "--layout" => options.layouts.push(value()?),
+                "--size" => options.sizes.push(parse_number(&value()?)?),
+                "--frames" => options.frames = parse_number(&value()?)?,
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+
+        for layout in &options.layouts {
+            if !QUANTIZATION_ERRORS.iter().any(|(known, _)| known == layout) {
+                return Err(format!("{} is not a quantized layout", layout));
+            }
+        }
+        if options.sizes.is_empty() {
+            options.sizes.extend(DEFAULT_SIZES);
+        }
+        if options.frames == 0 {
+            return Err("--frames must be at least 1".to_string());
+        }
+
+        Ok(options)
+    }
+}
+
+//How far each quantized layout's positions end up from an f32 reference run over the same inputs
+pub fn quantization(options: &QuantizationOptions) -> Result<(), String> {
+    println!(
+        "{:<24} {:>8} {:>14} {:>14} {:>14}",
+        "layout", "size", "max abs", "mean abs", "rms"
+    );
+
+    for &size in &options.sizes {
+        for (layout, measure) in QUANTIZATION_ERRORS.iter().filter(|(layout, _)| {
+            options.layouts.is_empty() || options.layouts.iter().any(|l| l == layout)
+        }) {
+            let error = measure(size, options.frames);
+
+            println!(
+                "{:<24} {:>8} {:>14e} {:>14e} {:>14e}",
+                layout, size, error.max_abs, error.mean_abs, error.rms
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::criterion_results::{self, Measurement};
+use crate::fingerprint::Fingerprint;
+
+pub const USAGE: &str = "\
+usage: bevy-simd-bench report [options]
+
+report options:
+  --criterion-dir <dir>   criterion output to read (default: target/criterion)
+  --group <name>          only report this benchmark group, may be repeated (default: all)
+  --baseline [<group>=]<id>
+                          benchmark speedups are relative to, in the given group or in every
+                          group without its own, may be repeated (default: vec3_nochangedetect).
+                          Tables without their baseline leave out the speedup column
+  --output <file>         write the Markdown here instead of stdout";
+
+//The plain AoS `Vec3` benchmark
+const DEFAULT_BASELINE: &str = "vec3_nochangedetect";
+
+//Benchmark each group's speedups are relative to
+pub struct Baselines {
+    pub default: String,
+    pub groups: BTreeMap<String, String>,
+}
+
+impl Baselines {
+    pub fn get(&self, group: &str) -> &str {
+        self.groups.get(group).unwrap_or(&self.default)
+    }
+}
+
+pub struct ReportOptions {
+    pub criterion_dir: PathBuf,
+    pub groups: Vec<String>,
+    pub baselines: Baselines,
+    pub output: Option<PathBuf>,
+}
+
+impl ReportOptions {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            criterion_dir: criterion_results::DEFAULT_CRITERION_DIR.into(),
+            groups: Vec::new(),
+            baselines: Baselines {
+                default: DEFAULT_BASELINE.to_string(),
+                groups: BTreeMap::new(),
+            },
+            output: None,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {}", arg))
+            };
+
+            match arg.as_str() {
+                "--criterion-dir" => options.criterion_dir = value()?.into(),
+                "--group" => options.groups.push(value()?),
+                "--baseline" => {
+                    let baseline = value()?;
+                    match baseline.split_once('=') {
+                        Some((group, id)) => {
+                            options
+                                .baselines
+                                .groups
+                                .insert(group.to_string(), id.to_string());
+                        }
+                        None => options.baselines.default = baseline,
+                    }
+                }
+                "--output" => options.output = Some(value()?.into()),
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+pub fn report(options: &ReportOptions) -> Result<(), String> {
+    let measurements: Vec<Measurement> = criterion_results::load(&options.criterion_dir)?
+        .into_iter()
+        .filter(|m| options.groups.is_empty() || options.groups.contains(&m.group))
+        .collect();
+
+    if measurements.is_empty() {
+        return Err("no results in the requested groups".to_string());
+    }
+
+    let markdown = render(&measurements, &options.baselines, &Fingerprint::collect());
+
+    match &options.output {
+        Some(path) => fs::write(path, markdown)
+            .map_err(|e| format!("couldn't write {}: {}", path.display(), e)),
+        None => {
+            print!("{}", markdown);
+            Ok(())
+        }
+    }
+}
+
+//One table per group and size, layouts ranked fastest first, with a speedup column when the group's
+//baseline ran at that size
+pub fn render(
+    measurements: &[Measurement],
+    baselines: &Baselines,
+    fingerprint: &Fingerprint,
+) -> String {
+    let mut tables: BTreeMap<(&str, u64), Vec<&Measurement>> = BTreeMap::new();
+    for m in measurements {
+        tables.entry((&m.group, m.size)).or_default().push(m);
+    }
+
+    let mut out = String::new();
+
+    writeln!(out, "## Machine\n\n{}\n", fingerprint).unwrap();
+
+    for ((group, size), mut rows) in tables {
+        rows.sort_by(|a, b| a.mean_ns.total_cmp(&b.mean_ns));
+
+        let baseline = baselines.get(group);
+        let baseline_ns = rows
+            .iter()
+            .find(|m| m.benchmark == baseline)
+            .map(|m| m.mean_ns);
+
+        writeln!(out, "## {} ({} entities)\n", group, size).unwrap();
+        write!(out, "| Rank | Benchmark | Mean | ns/element | Melem/s |").unwrap();
+        if baseline_ns.is_some() {
+            write!(out, " Speedup vs `{}` |", baseline).unwrap();
+        }
+        write!(out, "\n|---:|---|---:|---:|---:|").unwrap();
+        if baseline_ns.is_some() {
+            write!(out, "---:|").unwrap();
+        }
+        writeln!(out).unwrap();
+
+        for (rank, m) in rows.iter().enumerate() {
+            write!(
+                out,
+                "| {} | `{}` | {} | {:.3} | {:.1} |",
+                rank + 1,
+                m.benchmark,
+                format_duration(m.mean_ns),
+                m.ns_per_element(),
+                m.elements_per_second() / 1e6
+            )
+            .unwrap();
+            if let Some(baseline_ns) = baseline_ns {
+                write!(out, " {:.2}x |", baseline_ns / m.mean_ns).unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+
+        writeln!(out).unwrap();
+    }
+
+    out
+}
+
+fn format_duration(ns: f64) -> String {
+    if ns >= 1e6 {
+        format!("{:.2} ms", ns / 1e6)
+    } else if ns >= 1e3 {
+        format!("{:.2} µs", ns / 1e3)
+    } else {
+        format!("{:.1} ns", ns)
+    }
+}
@@ -0,0 +1,296 @@
+use std::str::FromStr;
+use std::time::Instant;
+
+use rand::rngs::ThreadRng;
+
+use crate::counters::Counters;
+use crate::impls::cache::{self, CacheLevel};
+use crate::impls::registry::{self, Case, CASES};
+use crate::stats::FrameStats;
+
+pub const DEFAULT_SIZES: [i32; 2] = [16383, 32767];
+pub const DEFAULT_FRAMES: usize = 1000;
+pub const DEFAULT_WARMUP_FRAMES: usize = 100;
+
+pub const USAGE: &str = "\
+usage: bevy-simd-bench run [options]
+       bevy-simd-bench list
+
+run options:
+  --layout <name>     layout to run, may be repeated (default: all)
+  --workload <name>   workload to run, may be repeated (default: all)
+  --size <n>          number of virtual entities, may be repeated (default: working sets at 50%,
+                      100% and 200% of each cache level, or 16383 32767 if those are unknown)
+  --frames <n>        timed frames per case (default: 1000)
+  --warmup <n>        untimed frames run before timing (default: 100)
+  --counters          also collect hardware performance counters (Linux perf_event_open) over
+                      a separate run of the same number of frames, reported per element";
+
+pub struct RunOptions {
+    pub layouts: Vec<String>,
+    pub workloads: Vec<String>,
+    pub sizes: Vec<i32>,
+    pub frames: usize,
+    pub warmup: usize,
+    pub counters: bool,
+}
+
+impl RunOptions {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            layouts: Vec::new(),
+            workloads: Vec::new(),
+            sizes: Vec::new(),
+            frames: DEFAULT_FRAMES,
+            warmup: DEFAULT_WARMUP_FRAMES,
+            counters: false,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {}", arg))
+            };
+
+            match arg.as_str() {
+                "--layout" => options.layouts.push(value()?),
+                "--workload" => options.workloads.push(value()?),
+                "--size" => options.sizes.push(parse_number(&value()?)?),
+                "--frames" => options.frames = parse_number(&value()?)?,
+                "--warmup" => options.warmup = parse_number(&value()?)?,
+                "--counters" => options.counters = true,
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+
+        if options.sizes.iter().any(|&size| size < 1) {
+            return Err("--size must be at least 1".to_string());
+        }
+        if options.frames == 0 {
+            return Err("--frames must be at least 1".to_string());
+        }
+
+        Ok(options)
+    }
+
+    pub fn cases(&self) -> Result<Vec<&'static Case>, String> {
+        for layout in &self.layouts {
+            if !CASES.iter().any(|case| case.layout == layout.as_str()) {
+                return Err(format!("unknown layout {} (see `list`)", layout));
+            }
+        }
+        for workload in &self.workloads {
+            if !CASES.iter().any(|case| case.workload == workload.as_str()) {
+                return Err(format!("unknown workload {} (see `list`)", workload));
+            }
+        }
+
+        let cases: Vec<_> = CASES
+            .iter()
+            .filter(|case| self.layouts.is_empty() || self.layouts.iter().any(|l| l == case.layout))
+            .filter(|case| {
+                self.workloads.is_empty() || self.workloads.iter().any(|w| w == case.workload)
+            })
+            .collect();
+
+        if cases.is_empty() {
+            return Err("no layout implements the requested workloads".to_string());
+        }
+
+        Ok(cases)
+    }
+
+    //The requested sizes, or the case's cache-relative sizes when none were given
+    pub fn sizes(&self, case: &Case, levels: &[CacheLevel]) -> Vec<i32> {
+        if !self.sizes.is_empty() {
+            return self.sizes.clone();
+        }
+
+        match registry::bytes_per_element(case.layout) {
+            Some(bytes) if !levels.is_empty() => cache::sizes(levels, bytes),
+            _ => DEFAULT_SIZES.to_vec(),
+        }
+    }
+}
+
+//The options shared by the subcommands sweeping one property of the world over the registry's layouts
+pub struct SweepOptions {
+    pub layouts: Vec<String>,
+    pub sizes: Vec<i32>,
+    pub frames: usize,
+    pub warmup: usize,
+}
+
+impl SweepOptions {
+    //Also returns the numbers given for each of the subcommand's own options in `sweeps`, each of
+    //which may be repeated
+    pub fn parse<T: FromStr, const N: usize>(
+        mut args: impl Iterator<Item = String>,
+        sweeps: [&str; N],
+    ) -> Result<(Self, [Vec<T>; N]), String> {
+        let mut options = Self {
+            layouts: Vec::new(),
+            sizes: Vec::new(),
+            frames: DEFAULT_FRAMES,
+            warmup: DEFAULT_WARMUP_FRAMES,
+        };
+        let mut swept = [(); N].map(|_| Vec::new());
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {}", arg))
+            };
+
+            match arg.as_str() {
+                "--layout" => options.layouts.push(value()?),
+                "--size" => options.sizes.push(parse_number(&value()?)?),
+                "--frames" => options.frames = parse_number(&value()?)?,
+                "--warmup" => options.warmup = parse_number(&value()?)?,
+                _ => match sweeps.iter().position(|&sweep| sweep == arg) {
+                    Some(i) => swept[i].push(parse_number(&value()?)?),
+                    None => return Err(format!("unknown option {}", arg)),
+                },
+            }
+        }
+
+        if options.sizes.is_empty() {
+            options.sizes.extend(DEFAULT_SIZES);
+        }
+        if options.sizes.iter().any(|&size| size < 1) {
+            return Err("--size must be at least 1".to_string());
+        }
+        if options.frames == 0 {
+            return Err("--frames must be at least 1".to_string());
+        }
+
+        Ok((options, swept))
+    }
+
+    //Errors on a requested layout that `has_kernel` rejects
+    pub fn check_layouts(
+        &self,
+        has_kernel: impl Fn(&str) -> bool,
+        kernel: &str,
+    ) -> Result<(), String> {
+        match self.layouts.iter().find(|layout| !has_kernel(layout)) {
+            Some(layout) => Err(format!("no {} kernel for layout {}", kernel, layout)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn selects(&self, layout: &str) -> bool {
+        self.layouts.is_empty() || self.layouts.iter().any(|l| l == layout)
+    }
+}
+
+pub fn parse_number<T: FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("expected a number, got {}", value))
+}
+
+pub fn list() {
+    for case in CASES {
+        println!("{:<40} {}", case.layout, case.workload);
+    }
+}
+
+pub fn run(options: &RunOptions) -> Result<(), String> {
+    let cases = options.cases()?;
+
+    println!(
+        "{:<60} {:>8} {:>12} {:>12} {:>12} {:>12} {:>12} {:>10} {:>8}",
+        "case", "size", "min ns", "median ns", "mean ns", "p95 ns", "stddev ns", "ns/elem", "GB/s"
+    );
+
+    let levels = cache::levels();
+    if options.sizes.is_empty() && !levels.is_empty() {
+        let levels: Vec<_> = levels.iter().map(ToString::to_string).collect();
+        println!("sizing working sets around {}", levels.join(", "));
+    }
+
+    let mut counters = options.counters.then(Counters::open);
+
+    for case in &cases {
+        for size in options.sizes(case, &levels) {
+            let stats = run_case(case, size, options.frames, options.warmup);
+            //bytes per ns is GB/s
+            let bandwidth = case
+                .traffic
+                .map(|traffic| format!("{:.2}", traffic().bytes() * size as f64 / stats.mean))
+                .unwrap_or_else(|| "-".to_string());
+
+            println!(
+                "{:<60} {:>8} {:>12.0} {:>12.0} {:>12.0} {:>12.0} {:>12.0} {:>10.3} {:>8}",
+                case.name(),
+                size,
+                stats.min,
+                stats.median,
+                stats.mean,
+                stats.p95,
+                stats.stddev,
+                stats.mean / size as f64,
+                bandwidth
+            );
+
+            if let Some(counters) = &mut counters {
+                count_case(counters, case, size, options.frames, options.warmup);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn run_case(case: &Case, size: i32, frames: usize, warmup: usize) -> FrameStats {
+    time_frames((case.build)(size), frames, warmup)
+}
+
+//Runs `warmup` untimed frames, then times each of `frames` frames
+pub fn time_frames(
+    mut frame: impl FnMut(&mut ThreadRng),
+    frames: usize,
+    warmup: usize,
+) -> FrameStats {
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..warmup {
+        frame(&mut rng);
+    }
+
+    let frame_times: Vec<_> = (0..frames)
+        .map(|_| {
+            let start = Instant::now();
+            frame(&mut rng);
+            start.elapsed()
+        })
+        .collect();
+
+    FrameStats::new(&frame_times)
+}
+
+//Runs the case again with the counters enabled around all of its frames, so the timings above
+//aren't disturbed, and prints the counts per element
+fn count_case(counters: &mut Counters, case: &Case, size: i32, frames: usize, warmup: usize) {
+    let mut kernel = (case.build)(size);
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..warmup {
+        kernel(&mut rng);
+    }
+
+    counters.start();
+    for _ in 0..frames {
+        kernel(&mut rng);
+    }
+    let counts = counters.stop();
+
+    let elements = frames as f64 * size as f64;
+    for (event, count) in counts {
+        match count {
+            Some(count) => println!("    {:<20} {:>12.4} /elem", event.name(), count / elements),
+            None => println!("    {:<20} {:>12}", event.name(), "unavailable"),
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+//Summary of a set of per-frame timings, in nanoseconds
+#[derive(Debug, Copy, Clone)]
+pub struct FrameStats {
+    pub frames: usize,
+    pub min: f64,
+    pub median: f64,
+    pub mean: f64,
+    pub p95: f64,
+    pub max: f64,
+    pub stddev: f64,
+}
+
+impl FrameStats {
+    pub fn new(frame_times: &[Duration]) -> Self {
+        assert!(!frame_times.is_empty(), "no frames were timed");
+
+        let mut nanos: Vec<f64> = frame_times.iter().map(|t| t.as_nanos() as f64).collect();
+        nanos.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let frames = nanos.len();
+        let mean = nanos.iter().sum::<f64>() / frames as f64;
+        let variance = nanos.iter().map(|t| (t - mean) * (t - mean)).sum::<f64>() / frames as f64;
+
+        Self {
+            frames,
+            min: nanos[0],
+            median: percentile(&nanos, 0.5),
+            mean,
+            p95: percentile(&nanos, 0.95),
+            max: nanos[frames - 1],
+            stddev: variance.sqrt(),
+        }
+    }
+}
+
+//Nearest-rank percentile of already sorted values
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = (p * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}